@@ -0,0 +1,83 @@
+use std::fmt::Write as _;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+use sdl2::render::RendererInfo;
+
+use crate::config::Config;
+use crate::frame_stats;
+use crate::json;
+use crate::listing;
+
+/// Records every frame time for a fixed time so it can be written out as a report.
+pub struct Benchmark {
+    length: Duration,
+    started: Instant,
+    frames: Vec<Duration>,
+}
+
+impl Benchmark {
+    pub fn new(length: Duration) -> Self {
+        Benchmark {
+            length,
+            started: Instant::now(),
+            frames: Vec::new(),
+        }
+    }
+
+    pub fn push(&mut self, frame_time: Duration) {
+        self.frames.push(frame_time);
+    }
+
+    pub fn is_done(&self) -> bool {
+        self.started.elapsed() >= self.length
+    }
+
+    /// Writes `<path>.json` with the setup, summary and every frame, and `<path>.csv` with
+    /// just the frames. Returns both paths.
+    pub fn write(
+        &self,
+        path: &Path,
+        renderer: &RendererInfo,
+        output_size: (u32, u32),
+        config: &Config,
+    ) -> std::io::Result<(PathBuf, PathBuf)> {
+        let json_path = path.with_extension("json");
+        let csv_path = path.with_extension("csv");
+        std::fs::write(&json_path, format!("{}\n", self.to_json(renderer, output_size, config)))?;
+        std::fs::write(&csv_path, self.to_csv())?;
+        Ok((json_path, csv_path))
+    }
+
+    fn to_json(&self, renderer: &RendererInfo, output_size: (u32, u32), config: &Config) -> json::Value {
+        let frames: Vec<f64> = self.frames.iter().map(|f| f.as_secs_f64() * 1000.0).collect();
+        json::Value::object(vec![
+            ("length", self.length.as_secs_f64().into()),
+            ("completed", self.is_done().into()),
+            ("renderer", listing::renderer_json(renderer)),
+            (
+                "output_size",
+                json::Value::object(vec![
+                    ("width", output_size.0.into()),
+                    ("height", output_size.1.into()),
+                ]),
+            ),
+            ("vsync", config.vsync.into()),
+            ("fps_cap", config.fps_cap().into()),
+            ("limiter", config.limiter.key().into()),
+            (
+                "summary",
+                frame_stats::summarize(&self.frames).map(|s| s.to_json()).into(),
+            ),
+            ("frames_ms", frames.into()),
+        ])
+    }
+
+    fn to_csv(&self) -> String {
+        let mut csv = String::from("frame,duration_ms\n");
+        for (i, frame) in self.frames.iter().enumerate() {
+            writeln!(csv, "{},{}", i, frame.as_secs_f64() * 1000.0).unwrap();
+        }
+        csv
+    }
+}
@@ -0,0 +1,259 @@
+use std::time::{Duration, Instant};
+
+use sdl2::event::Event;
+use sdl2::keyboard::{Mod, Scancode};
+
+/// How long modifier presses are held back waiting for the rest of the chord.
+const HOLD: Duration = Duration::from_secs(1);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Modifier {
+    Ctrl,
+    Shift,
+    Alt,
+    Gui,
+}
+
+impl Modifier {
+    const ALL: [Modifier; 4] = [Modifier::Ctrl, Modifier::Shift, Modifier::Alt, Modifier::Gui];
+
+    fn key(&self) -> &'static str {
+        match self {
+            Modifier::Ctrl => "ctrl",
+            Modifier::Shift => "shift",
+            Modifier::Alt => "alt",
+            Modifier::Gui => "gui",
+        }
+    }
+
+    /// Either side counts.
+    fn mods(&self) -> Mod {
+        match self {
+            Modifier::Ctrl => Mod::LCTRLMOD | Mod::RCTRLMOD,
+            Modifier::Shift => Mod::LSHIFTMOD | Mod::RSHIFTMOD,
+            Modifier::Alt => Mod::LALTMOD | Mod::RALTMOD,
+            Modifier::Gui => Mod::LGUIMOD | Mod::RGUIMOD,
+        }
+    }
+
+    fn scancodes(&self) -> [Scancode; 2] {
+        match self {
+            Modifier::Ctrl => [Scancode::LCtrl, Scancode::RCtrl],
+            Modifier::Shift => [Scancode::LShift, Scancode::RShift],
+            Modifier::Alt => [Scancode::LAlt, Scancode::RAlt],
+            Modifier::Gui => [Scancode::LGui, Scancode::RGui],
+        }
+    }
+}
+
+/// A key pressed while holding some modifiers, e.g. `ctrl+shift+p`. Keys are matched by
+/// where they are on the keyboard, not what the layout says they are.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Chord {
+    modifiers: Vec<Modifier>,
+    key: Scancode,
+}
+
+impl Chord {
+    pub fn key(&self) -> String {
+        let mut parts: Vec<String> = self.modifiers.iter().map(|m| m.key().to_string()).collect();
+        parts.push(self.key.name().to_lowercase());
+        parts.join("+")
+    }
+
+    /// Modifiers (ctrl, shift, alt, gui) then a key name as SDL spells it, joined by `+`.
+    /// Works before SDL is initialized, scancode names don't depend on the keyboard layout.
+    pub fn from_key(key: &str) -> Option<Chord> {
+        let mut parts: Vec<&str> = key.split('+').map(str::trim).collect();
+        let key = Scancode::from_name(parts.pop()?)?;
+        let mut modifiers = Vec::new();
+        for part in parts {
+            let modifier = Modifier::ALL.into_iter().find(|m| m.key().eq_ignore_ascii_case(part))?;
+            if !modifiers.contains(&modifier) {
+                modifiers.push(modifier);
+            }
+        }
+        Some(Chord { modifiers, key })
+    }
+
+    fn held_in(&self, keymod: Mod) -> bool {
+        self.modifiers.iter().all(|m| keymod.intersects(m.mods()))
+    }
+
+    fn is_modifier(&self, scancode: Scancode) -> bool {
+        self.modifiers.iter().any(|m| m.scancodes().contains(&scancode))
+    }
+}
+
+#[derive(Debug)]
+pub enum Filtered {
+    Event(Instant, Event),
+    /// The whole chord was pressed.
+    Chord(Instant),
+}
+
+impl Filtered {
+    pub fn at(&self) -> Instant {
+        match self {
+            Filtered::Event(at, _) | Filtered::Chord(at) => *at,
+        }
+    }
+}
+
+/// Sits between SDL and the event loop, so reaching for the chord doesn't count as input.
+/// Presses of the chord's modifiers are held back until the chord is finished, in which
+/// case they're dropped, or until it's clear it won't be, in which case they're let through
+/// with the time they actually happened.
+pub struct ChordFilter {
+    chord: Option<Chord>,
+    held: Vec<(Instant, Event)>,
+}
+
+impl ChordFilter {
+    /// Without a chord everything goes straight through.
+    pub fn new(chord: Option<Chord>) -> Self {
+        ChordFilter { chord, held: Vec::new() }
+    }
+
+    /// Queues `event` into `out`, after whatever was held back if it's input.
+    pub fn feed(&mut self, at: Instant, event: Event, is_input: bool, out: &mut Vec<Filtered>) {
+        let chord = match &self.chord {
+            Some(c) => c,
+            None => {
+                out.push(Filtered::Event(at, event));
+                return;
+            }
+        };
+        match &event {
+            Event::KeyDown {
+                scancode: Some(k),
+                keymod,
+                repeat,
+                ..
+            } if *k == chord.key && chord.held_in(*keymod) => {
+                // holding it down doesn't toggle anything again, nor count as input
+                if !repeat {
+                    self.held.clear();
+                    out.push(Filtered::Chord(at));
+                }
+            }
+            Event::KeyDown { scancode: Some(k), .. } if chord.is_modifier(*k) => self.held.push((at, event)),
+            // let go without finishing the chord
+            Event::KeyUp { scancode: Some(k), .. } if chord.is_modifier(*k) => {
+                self.flush(out);
+                out.push(Filtered::Event(at, event));
+            }
+            _ => {
+                if is_input {
+                    self.flush(out);
+                }
+                out.push(Filtered::Event(at, event));
+            }
+        }
+    }
+
+    /// Lets through presses that have waited too long for the rest of the chord.
+    pub fn expire(&mut self, now: Instant, out: &mut Vec<Filtered>) {
+        if self.held.first().is_some_and(|(at, _)| now - *at >= HOLD) {
+            self.flush(out);
+        }
+    }
+
+    fn flush(&mut self, out: &mut Vec<Filtered>) {
+        out.extend(self.held.drain(..).map(|(at, event)| Filtered::Event(at, event)));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn filter() -> ChordFilter {
+        ChordFilter::new(Some(Chord {
+            modifiers: vec![Modifier::Ctrl],
+            key: Scancode::P,
+        }))
+    }
+
+    fn key_down(scancode: Scancode, keymod: Mod) -> Event {
+        Event::KeyDown {
+            timestamp: 0,
+            window_id: 0,
+            keycode: None,
+            scancode: Some(scancode),
+            keymod,
+            repeat: false,
+        }
+    }
+
+    fn key_up(scancode: Scancode) -> Event {
+        Event::KeyUp {
+            timestamp: 0,
+            window_id: 0,
+            keycode: None,
+            scancode: Some(scancode),
+            keymod: Mod::NOMOD,
+            repeat: false,
+        }
+    }
+
+    /// What came out, as (time since `start`, event or `None` for the chord).
+    fn drain(out: &mut Vec<Filtered>, start: Instant) -> Vec<(Duration, Option<Event>)> {
+        out.drain(..)
+            .map(|f| match f {
+                Filtered::Event(at, event) => (at - start, Some(event)),
+                Filtered::Chord(at) => (at - start, None),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn finished_chord_swallows_its_modifiers() {
+        let (mut filter, mut out, start) = (filter(), Vec::new(), Instant::now());
+        filter.feed(start, key_down(Scancode::LCtrl, Mod::LCTRLMOD), true, &mut out);
+        assert!(out.is_empty());
+        let pressed = start + Duration::from_millis(300);
+        filter.feed(pressed, key_down(Scancode::P, Mod::LCTRLMOD), true, &mut out);
+        assert_eq!(drain(&mut out, start), [(Duration::from_millis(300), None)]);
+        // nothing left to let through later
+        filter.expire(start + HOLD * 2, &mut out);
+        assert!(out.is_empty());
+    }
+
+    #[test]
+    fn lone_modifier_comes_out_with_its_own_time() {
+        let (mut filter, mut out, start) = (filter(), Vec::new(), Instant::now());
+        let ctrl = key_down(Scancode::RCtrl, Mod::RCTRLMOD);
+        filter.feed(start, ctrl.clone(), true, &mut out);
+        filter.expire(start + HOLD - Duration::from_millis(1), &mut out);
+        assert!(out.is_empty());
+        filter.expire(start + HOLD, &mut out);
+        assert_eq!(drain(&mut out, start), [(Duration::ZERO, Some(ctrl))]);
+    }
+
+    #[test]
+    fn letting_go_early_lets_the_press_through_first() {
+        let (mut filter, mut out, start) = (filter(), Vec::new(), Instant::now());
+        let ctrl = key_down(Scancode::LCtrl, Mod::LCTRLMOD);
+        filter.feed(start, ctrl.clone(), true, &mut out);
+        let released = start + Duration::from_millis(200);
+        filter.feed(released, key_up(Scancode::LCtrl), false, &mut out);
+        assert_eq!(
+            drain(&mut out, start),
+            [(Duration::ZERO, Some(ctrl)), (Duration::from_millis(200), Some(key_up(Scancode::LCtrl)))]
+        );
+    }
+
+    #[test]
+    fn other_input_lets_the_press_through_first() {
+        let (mut filter, mut out, start) = (filter(), Vec::new(), Instant::now());
+        let ctrl = key_down(Scancode::LCtrl, Mod::LCTRLMOD);
+        let copy = key_down(Scancode::C, Mod::LCTRLMOD);
+        filter.feed(start, ctrl.clone(), true, &mut out);
+        filter.feed(start + Duration::from_millis(50), copy.clone(), true, &mut out);
+        assert_eq!(
+            drain(&mut out, start),
+            [(Duration::ZERO, Some(ctrl)), (Duration::from_millis(50), Some(copy))]
+        );
+    }
+}
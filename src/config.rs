@@ -0,0 +1,526 @@
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use sdl2::pixels::Color;
+
+use crate::error::Sink;
+use crate::frame_graph::GraphMode;
+use crate::frame_limiter::Strategy;
+use crate::log::Level;
+use crate::sensitivity::{Sensitivity, DEFAULT_AXIS_THRESHOLD};
+use crate::chord::Chord;
+use crate::countdown::Style;
+use crate::idle::Source;
+use crate::session::{CountdownInput, FocusPolicy, Reason, Reasons, Rules};
+
+const ENV_PREFIX: &str = "NOTHING_";
+
+const KEYS: [&str; 34] = [
+    "fps",
+    "unlimited",
+    "limiter",
+    "stats_window",
+    "frame_graph",
+    "vsync",
+    "selected_gpu_renderer",
+    "window_mode",
+    "size",
+    "display",
+    "font",
+    "font_size",
+    "hud_font_size",
+    "foreground",
+    "background",
+    "countdown",
+    "countdown_input",
+    "countdown_style",
+    "pause_chord",
+    "focus_policy",
+    "grace_period",
+    "ignore_first_input",
+    "result_duration",
+    "wait_for_key",
+    "motion_dead_zone",
+    "min_wheel_delta",
+    "axis_threshold",
+    "ignore",
+    "evdev",
+    "idle_source",
+    "record_journal",
+    "errors",
+    "log_level",
+    "log_file",
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WindowMode {
+    /// Exclusive fullscreen, switches the display mode.
+    Fullscreen,
+    /// Fullscreen at the desktop resolution.
+    Desktop,
+    Windowed,
+    /// Window without decorations.
+    Borderless,
+}
+
+impl WindowMode {
+    pub fn key(&self) -> &'static str {
+        match self {
+            WindowMode::Fullscreen => "fullscreen",
+            WindowMode::Desktop => "desktop",
+            WindowMode::Windowed => "windowed",
+            WindowMode::Borderless => "borderless",
+        }
+    }
+
+    pub fn from_key(key: &str) -> Option<WindowMode> {
+        [
+            WindowMode::Fullscreen,
+            WindowMode::Desktop,
+            WindowMode::Windowed,
+            WindowMode::Borderless,
+        ]
+        .into_iter()
+            .find(|m| m.key() == key)
+    }
+}
+
+/// Everything that can be set from the config file, the environment or the command line.
+#[derive(Debug, Clone)]
+pub struct Config {
+    pub fps: u32,
+    pub unlimited: bool,
+    pub limiter: Strategy,
+    /// How far back the HUD frame statistics look.
+    pub stats_window: Duration,
+    /// How the frame time graph starts out, F3 cycles it.
+    pub frame_graph: GraphMode,
+    pub vsync: bool,
+    pub selected_gpu_renderer: Option<usize>,
+    pub window_mode: WindowMode,
+    /// Window size, also the resolution asked for in exclusive fullscreen.
+    pub size: (u32, u32),
+    /// 1 based, like the renderer index. `None` leaves it to SDL.
+    pub display: Option<usize>,
+    /// `None` uses the bundled Roboto.
+    pub font: Option<PathBuf>,
+    pub font_size: u16,
+    pub hud_font_size: u16,
+    pub foreground: Color,
+    pub background: Color,
+    /// Zero starts runs right away.
+    pub countdown: Duration,
+    pub countdown_input: CountdownInput,
+    pub countdown_style: Style,
+    /// Pauses and resumes a run, `None` turns pausing off.
+    pub pause_chord: Option<Chord>,
+    /// What losing focus, minimising or hiding the window does to a run.
+    pub focus_policy: FocusPolicy,
+    pub grace_period: Duration,
+    pub ignore_first_input: bool,
+    pub result_duration: Duration,
+    pub wait_for_key: bool,
+    pub motion_dead_zone: u32,
+    pub min_wheel_delta: u32,
+    pub axis_threshold: u16,
+    pub ignore: Reasons,
+    /// Also read `/dev/input` (Linux only), for input that goes elsewhere while the window is in the background.
+    pub evdev: bool,
+    /// Where the OS's idle time comes from, to check runs against.
+    pub idle_source: Source,
+    /// Save every run's input to the journal directory.
+    pub record_journal: bool,
+    /// Where a fatal error gets reported.
+    pub errors: Sink,
+    pub log_level: Level,
+    /// Where the log gets appended, besides stderr.
+    pub log_file: Option<PathBuf>,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Config {
+            fps: 60,
+            unlimited: false,
+            limiter: Strategy::Deadline,
+            stats_window: Duration::from_secs(5),
+            frame_graph: GraphMode::Compact,
+            vsync: false,
+            selected_gpu_renderer: None,
+            window_mode: WindowMode::Desktop,
+            size: (800, 600),
+            display: None,
+            font: None,
+            font_size: 45,
+            hud_font_size: 15,
+            foreground: Color::WHITE,
+            background: Color::BLACK,
+            countdown: Duration::from_secs(3),
+            countdown_input: CountdownInput::Ignore,
+            countdown_style: Style::Fade,
+            pause_chord: Chord::from_key("ctrl+shift+p"),
+            focus_policy: FocusPolicy::Fail,
+            grace_period: Duration::ZERO,
+            ignore_first_input: true,
+            result_duration: Duration::from_secs(5),
+            wait_for_key: false,
+            motion_dead_zone: 0,
+            min_wheel_delta: 0,
+            axis_threshold: DEFAULT_AXIS_THRESHOLD,
+            ignore: Reasons::default(),
+            evdev: false,
+            idle_source: Source::Auto,
+            record_journal: false,
+            errors: Sink::MessageBox,
+            log_level: Level::Info,
+            log_file: None,
+        }
+    }
+}
+
+impl Config {
+    pub fn default_path() -> Option<PathBuf> {
+        crate::paths::config_dir().map(|d| d.join("config.toml"))
+    }
+
+    /// Overrides with whatever the TOML file at `path` sets. A missing file is fine.
+    pub fn merge_file(&mut self, path: &Path) -> Result<(), String> {
+        let text = match std::fs::read_to_string(path) {
+            Ok(t) => t,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(()),
+            Err(e) => return Err(format!("failed to read {}: {}", path.display(), e)),
+        };
+        let table = text
+            .parse::<toml::Table>()
+            .map_err(|e| format!("{}: {}", path.display(), e))?;
+        for (key, value) in &table {
+            self.set(key, value)
+                .map_err(|e| format!("{}: {}", path.display(), e))?;
+        }
+        Ok(())
+    }
+
+    /// Overrides with `NOTHING_<KEY>` environment variables, e.g. `NOTHING_FPS=144`.
+    /// Variables that don't name a setting are left alone.
+    pub fn merge_env(&mut self, vars: impl Iterator<Item = (String, String)>) -> Result<(), String> {
+        for (name, value) in vars {
+            let key = match name.strip_prefix(ENV_PREFIX) {
+                Some(k) => k.to_lowercase(),
+                None => continue,
+            };
+            if KEYS.contains(&key.as_str()) {
+                self.set(&key, &toml::Value::String(value))
+                    .map_err(|e| format!("{}: {}", name, e))?;
+            }
+        }
+        Ok(())
+    }
+
+    pub fn set(&mut self, key: &str, value: &toml::Value) -> Result<(), String> {
+        match key {
+            "fps" => {
+                self.fps = parse(value)?;
+                if self.fps == 0 {
+                    return Err("fps has to be at least 1, use unlimited = true for no cap".to_string());
+                }
+            }
+            "unlimited" => self.unlimited = parse_bool(value)?,
+            "limiter" => {
+                let s = as_str(value)?;
+                self.limiter = Strategy::from_key(s).ok_or_else(|| format!("unknown limiter `{}`", s))?
+            }
+            "stats_window" => {
+                let secs: f64 = parse(value)?;
+                self.stats_window = Duration::try_from_secs_f64(secs)
+                    .ok()
+                    .filter(|w| !w.is_zero())
+                    .ok_or_else(|| format!("invalid stats_window `{}`", secs))?
+            }
+            "frame_graph" => {
+                let s = as_str(value)?;
+                self.frame_graph = GraphMode::from_key(s)
+                    .ok_or_else(|| format!("unknown frame_graph `{}`", s))?
+            }
+            "vsync" => self.vsync = parse_bool(value)?,
+            "selected_gpu_renderer" => self.selected_gpu_renderer = Some(parse(value)?),
+            "window_mode" => {
+                let s = as_str(value)?;
+                self.window_mode = WindowMode::from_key(s)
+                    .ok_or_else(|| format!("unknown window_mode `{}`", s))?
+            }
+            "size" => self.size = parse_size(as_str(value)?)?,
+            "display" => self.display = Some(parse(value)?),
+            "font" => {
+                let s = as_str(value)?;
+                self.font = if s.is_empty() { None } else { Some(PathBuf::from(s)) }
+            }
+            "font_size" => self.font_size = parse(value)?,
+            "hud_font_size" => self.hud_font_size = parse(value)?,
+            "foreground" => self.foreground = parse_color(as_str(value)?)?,
+            "background" => self.background = parse_color(as_str(value)?)?,
+            "countdown" => {
+                let secs: f64 = parse(value)?;
+                self.countdown = Duration::try_from_secs_f64(secs)
+                    .map_err(|_| format!("invalid countdown `{}`", secs))?
+            }
+            "countdown_input" => {
+                let s = as_str(value)?;
+                self.countdown_input = CountdownInput::from_key(s)
+                    .ok_or_else(|| format!("unknown countdown_input `{}`", s))?
+            }
+            "countdown_style" => {
+                let s = as_str(value)?;
+                self.countdown_style = Style::from_key(s)
+                    .ok_or_else(|| format!("unknown countdown_style `{}`", s))?
+            }
+            "pause_chord" => {
+                let s = as_str(value)?;
+                self.pause_chord = if s.is_empty() {
+                    None
+                } else {
+                    Some(Chord::from_key(s).ok_or_else(|| format!("invalid pause_chord `{}`", s))?)
+                }
+            }
+            "focus_policy" => {
+                let s = as_str(value)?;
+                self.focus_policy = FocusPolicy::from_key(s)
+                    .ok_or_else(|| format!("unknown focus_policy `{}`", s))?
+            }
+            "grace_period" => {
+                let secs: f64 = parse(value)?;
+                self.grace_period = Duration::try_from_secs_f64(secs)
+                    .map_err(|_| format!("invalid grace_period `{}`", secs))?
+            }
+            "ignore_first_input" => self.ignore_first_input = parse_bool(value)?,
+            "result_duration" => {
+                let secs: f64 = parse(value)?;
+                self.result_duration = Duration::try_from_secs_f64(secs)
+                    .map_err(|_| format!("invalid result_duration `{}`", secs))?
+            }
+            "wait_for_key" => self.wait_for_key = parse_bool(value)?,
+            "motion_dead_zone" => self.motion_dead_zone = parse(value)?,
+            "min_wheel_delta" => self.min_wheel_delta = parse(value)?,
+            "axis_threshold" => self.axis_threshold = parse(value)?,
+            "ignore" => {
+                self.ignore = Reasons::default();
+                for key in as_list(value)? {
+                    match Reason::from_key(&key) {
+                        Some(Reason::FocusLost) => {
+                            return Err("focus_lost isn't a device, use focus_policy = \"ignore\"".to_string())
+                        }
+                        Some(reason) => self.ignore.insert(reason),
+                        None => return Err(format!("unknown device `{}`", key)),
+                    }
+                }
+            }
+            "evdev" => self.evdev = parse_bool(value)?,
+            "idle_source" => {
+                let s = as_str(value)?;
+                self.idle_source = Source::from_key(s).ok_or_else(|| format!("unknown idle_source `{}`", s))?
+            }
+            "record_journal" => self.record_journal = parse_bool(value)?,
+            "errors" => {
+                let s = as_str(value)?;
+                self.errors = Sink::from_key(s).ok_or_else(|| "errors can't be empty".to_string())?
+            }
+            "log_level" => {
+                let s = as_str(value)?;
+                self.log_level = Level::from_key(s).ok_or_else(|| format!("unknown log_level `{}`", s))?
+            }
+            "log_file" => {
+                let s = as_str(value)?;
+                self.log_file = if s.is_empty() { None } else { Some(PathBuf::from(s)) }
+            }
+            _ => return Err(format!("unknown setting `{}`", key)),
+        }
+        Ok(())
+    }
+
+    /// `None` when uncapped.
+    pub fn fps_cap(&self) -> Option<u32> {
+        if self.unlimited {
+            None
+        } else {
+            Some(self.fps)
+        }
+    }
+
+    pub fn rules(&self) -> Rules {
+        Rules {
+            sensitivity: Sensitivity {
+                motion_dead_zone: self.motion_dead_zone,
+                min_wheel_delta: self.min_wheel_delta,
+                axis_threshold: self.axis_threshold,
+                ignored: self.ignore,
+            },
+            countdown: self.countdown,
+            countdown_input: self.countdown_input,
+            grace_period: self.grace_period,
+            ignore_first_input: self.ignore_first_input,
+            result_duration: self.result_duration,
+            wait_for_key: self.wait_for_key,
+            focus: self.focus_policy,
+        }
+    }
+
+    /// The effective configuration, in the same format the config file uses.
+    pub fn to_toml(&self) -> toml::Table {
+        let mut t = toml::Table::new();
+        t.insert("fps".into(), (self.fps as i64).into());
+        t.insert("unlimited".into(), self.unlimited.into());
+        t.insert("limiter".into(), self.limiter.key().into());
+        t.insert("stats_window".into(), self.stats_window.as_secs_f64().into());
+        t.insert("frame_graph".into(), self.frame_graph.key().into());
+        t.insert("vsync".into(), self.vsync.into());
+        if let Some(r) = self.selected_gpu_renderer {
+            t.insert("selected_gpu_renderer".into(), (r as i64).into());
+        }
+        t.insert("window_mode".into(), self.window_mode.key().into());
+        t.insert("size".into(), format!("{}x{}", self.size.0, self.size.1).into());
+        if let Some(d) = self.display {
+            t.insert("display".into(), (d as i64).into());
+        }
+        let font = match &self.font {
+            Some(f) => f.display().to_string(),
+            None => String::new(),
+        };
+        t.insert("font".into(), font.into());
+        t.insert("font_size".into(), (self.font_size as i64).into());
+        t.insert("hud_font_size".into(), (self.hud_font_size as i64).into());
+        t.insert("foreground".into(), format_color(self.foreground).into());
+        t.insert("background".into(), format_color(self.background).into());
+        t.insert("countdown".into(), self.countdown.as_secs_f64().into());
+        t.insert("countdown_input".into(), self.countdown_input.key().into());
+        t.insert("countdown_style".into(), self.countdown_style.key().into());
+        let pause_chord = match &self.pause_chord {
+            Some(c) => c.key(),
+            None => String::new(),
+        };
+        t.insert("pause_chord".into(), pause_chord.into());
+        t.insert("focus_policy".into(), self.focus_policy.key().into());
+        t.insert("grace_period".into(), self.grace_period.as_secs_f64().into());
+        t.insert("ignore_first_input".into(), self.ignore_first_input.into());
+        t.insert("result_duration".into(), self.result_duration.as_secs_f64().into());
+        t.insert("wait_for_key".into(), self.wait_for_key.into());
+        t.insert("motion_dead_zone".into(), (self.motion_dead_zone as i64).into());
+        t.insert("min_wheel_delta".into(), (self.min_wheel_delta as i64).into());
+        t.insert("axis_threshold".into(), (self.axis_threshold as i64).into());
+        let ignore: Vec<toml::Value> = self.ignore.iter().map(|r| r.key().into()).collect();
+        t.insert("ignore".into(), ignore.into());
+        t.insert("evdev".into(), self.evdev.into());
+        t.insert("idle_source".into(), self.idle_source.key().into());
+        t.insert("record_journal".into(), self.record_journal.into());
+        t.insert("errors".into(), self.errors.key().into());
+        t.insert("log_level".into(), self.log_level.key().into());
+        let log_file = match &self.log_file {
+            Some(f) => f.display().to_string(),
+            None => String::new(),
+        };
+        t.insert("log_file".into(), log_file.into());
+        t
+    }
+}
+
+fn as_str(value: &toml::Value) -> Result<&str, String> {
+    value
+        .as_str()
+        .ok_or_else(|| format!("expected a string, got `{}`", value))
+}
+
+/// Numbers can come in as TOML numbers or, from the environment, as strings.
+fn parse<T: std::str::FromStr>(value: &toml::Value) -> Result<T, String> {
+    let s = match value {
+        toml::Value::String(s) => s.trim().to_string(),
+        toml::Value::Integer(i) => i.to_string(),
+        toml::Value::Float(f) => f.to_string(),
+        v => return Err(format!("expected a number, got `{}`", v)),
+    };
+    s.parse::<T>().map_err(|_| format!("invalid number `{}`", s))
+}
+
+fn parse_bool(value: &toml::Value) -> Result<bool, String> {
+    match value {
+        toml::Value::Boolean(b) => Ok(*b),
+        toml::Value::String(s) => match s.trim().to_lowercase().as_str() {
+            "true" | "1" | "yes" | "on" => Ok(true),
+            "false" | "0" | "no" | "off" => Ok(false),
+            _ => Err(format!("expected true or false, got `{}`", s)),
+        },
+        v => Err(format!("expected true or false, got `{}`", v)),
+    }
+}
+
+/// Either a TOML array of strings or a comma separated string.
+fn as_list(value: &toml::Value) -> Result<Vec<String>, String> {
+    match value {
+        toml::Value::Array(items) => items.iter().map(|i| as_str(i).map(str::to_string)).collect(),
+        toml::Value::String(s) => Ok(s
+            .split(',')
+            .map(|i| i.trim().to_string())
+            .filter(|i| !i.is_empty())
+            .collect()),
+        v => Err(format!("expected a list, got `{}`", v)),
+    }
+}
+
+/// `WIDTHxHEIGHT`, e.g. `1280x720`
+pub fn parse_size(s: &str) -> Result<(u32, u32), String> {
+    let invalid = || format!("expected WIDTHxHEIGHT, got `{}`", s);
+    let (w, h) = s.split_once(['x', 'X']).ok_or_else(invalid)?;
+    let w = w.trim().parse::<u32>().map_err(|_| invalid())?;
+    let h = h.trim().parse::<u32>().map_err(|_| invalid())?;
+    if w == 0 || h == 0 {
+        return Err(invalid());
+    }
+    Ok((w, h))
+}
+
+/// `#rrggbb`
+fn parse_color(s: &str) -> Result<Color, String> {
+    let hex = s
+        .strip_prefix('#')
+        .filter(|h| h.len() == 6 && h.is_ascii())
+        .ok_or_else(|| format!("expected a #rrggbb colour, got `{}`", s))?;
+    let channel = |i: usize| {
+        u8::from_str_radix(&hex[i..i + 2], 16)
+            .map_err(|_| format!("expected a #rrggbb colour, got `{}`", s))
+    };
+    Ok(Color::RGB(channel(0)?, channel(2)?, channel(4)?))
+}
+
+fn format_color(c: Color) -> String {
+    format!("#{:02x}{:02x}{:02x}", c.r, c.g, c.b)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn keys_match_what_gets_set_and_printed() {
+        let mut config = Config {
+            selected_gpu_renderer: Some(1),
+            display: Some(2),
+            ..Config::default()
+        };
+        let table = config.to_toml();
+        let mut printed: Vec<&str> = table.keys().map(|k| k.as_str()).collect();
+        printed.sort_unstable();
+        let mut keys = KEYS.to_vec();
+        keys.sort_unstable();
+        assert_eq!(printed, keys);
+
+        // everything printed goes back in and comes out the same
+        for (key, value) in &table {
+            if let Err(e) = config.set(key, value) {
+                panic!("{} = {}: {}", key, value, e);
+            }
+        }
+        assert_eq!(config.to_toml(), table);
+    }
+
+    #[test]
+    fn unknown_keys_are_refused() {
+        assert!(Config::default().set("fps_cap", &toml::Value::Integer(60)).is_err());
+    }
+}
@@ -0,0 +1,218 @@
+use sdl2::controller::GameController;
+use sdl2::event::Event;
+use sdl2::joystick::{HatState, Joystick};
+
+use crate::log;
+use crate::session::Input;
+
+/// Keeps every connected gamepad and joystick open, SDL only sends their events while they are.
+pub struct Controllers {
+    game_controller: sdl2::GameControllerSubsystem,
+    joystick: sdl2::JoystickSubsystem,
+    controllers: Vec<GameController>,
+    joysticks: Vec<Joystick>,
+    known: Known,
+}
+
+impl Controllers {
+    pub fn init(ctx: &sdl2::Sdl) -> Result<Controllers, String> {
+        Ok(Controllers {
+            game_controller: ctx.game_controller()?,
+            joystick: ctx.joystick()?,
+            controllers: Vec::new(),
+            joysticks: Vec::new(),
+            known: Known::default(),
+        })
+    }
+
+    /// Opens and closes devices as they get plugged in and out. SDL also sends
+    /// an added event for everything already connected at startup.
+    pub fn handle(&mut self, event: &Event) {
+        match event {
+            Event::JoyDeviceAdded { which, .. } => {
+                if self.game_controller.is_game_controller(*which) {
+                    match self.game_controller.open(*which) {
+                        Ok(c) => {
+                            log::info!("controllers", "opened game controller #{} name={:?}", which, c.name());
+                            self.known.game_controllers.push(c.instance_id());
+                            self.controllers.push(c)
+                        }
+                        Err(e) => log::warn!("controllers", "failed to open game controller #{}: {}", which, e),
+                    }
+                } else {
+                    match self.joystick.open(*which) {
+                        Ok(j) => {
+                            log::info!("controllers", "opened joystick #{} name={:?}", which, j.name());
+                            // as good a guess as any, nobody holds a trigger down while plugging it in
+                            let rest = (0..j.num_axes()).map(|i| j.axis(i).unwrap_or(0)).collect();
+                            self.known.rest.push((j.instance_id(), rest));
+                            self.joysticks.push(j)
+                        }
+                        Err(e) => log::warn!("controllers", "failed to open joystick #{}: {}", which, e),
+                    }
+                }
+            }
+            Event::JoyDeviceRemoved { which, .. } => {
+                log::info!("controllers", "device #{} removed", which);
+                self.controllers.retain(|c| c.instance_id() != *which);
+                self.joysticks.retain(|j| j.instance_id() != *which);
+                self.known.game_controllers.retain(|id| id != which);
+                self.known.rest.retain(|(id, _)| id != which);
+            }
+            _ => {}
+        }
+    }
+
+    /// The input in a controller or joystick event, `None` for the rest.
+    pub fn input(&self, event: &Event) -> Option<Input> {
+        self.known.input(event)
+    }
+}
+
+/// What the open devices' events get read with, by instance id.
+#[derive(Debug, Default)]
+struct Known {
+    /// A game controller's joystick sends its own events for the same presses.
+    game_controllers: Vec<u32>,
+    /// Where each raw joystick's axes sit when left alone. Triggers rest at -32768.
+    rest: Vec<(u32, Vec<i16>)>,
+}
+
+impl Known {
+    fn input(&self, event: &Event) -> Option<Input> {
+        match event {
+            Event::JoyButtonDown { which, .. } | Event::JoyHatMotion { which, .. } | Event::JoyAxisMotion { which, .. }
+                if self.game_controllers.contains(which) =>
+            {
+                None
+            }
+            Event::ControllerButtonDown { .. } | Event::JoyButtonDown { .. } => Some(Input::ControllerButtonDown),
+            Event::JoyHatMotion { state, .. } if *state != HatState::Centered => Some(Input::ControllerButtonDown),
+            // the controller API already has triggers resting at 0
+            Event::ControllerAxisMotion { value, .. } => Some(Input::ControllerAxis { value: *value }),
+            Event::JoyAxisMotion {
+                which, axis_idx, value, ..
+            } => {
+                let rest = self
+                    .rest
+                    .iter()
+                    .find(|(id, _)| id == which)
+                    .and_then(|(_, rest)| rest.get(usize::from(*axis_idx)))
+                    .copied()
+                    .unwrap_or(0);
+                Some(Input::ControllerAxis {
+                    value: from_rest(*value, rest),
+                })
+            }
+            _ => None,
+        }
+    }
+}
+
+/// How far `value` is from `rest`, as far as an `i16` goes.
+fn from_rest(value: i16, rest: i16) -> i16 {
+    (i32::from(value) - i32::from(rest)).clamp(i16::MIN.into(), i16::MAX.into()) as i16
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use sdl2::controller::{Axis, Button};
+
+    const PAD: u32 = 3;
+    const STICK: u32 = 4;
+
+    fn known() -> Known {
+        Known {
+            game_controllers: vec![PAD],
+            // a stick axis centered and a trigger let go
+            rest: vec![(STICK, vec![0, i16::MIN])],
+        }
+    }
+
+    fn axis(which: u32, axis_idx: u8, value: i16) -> Event {
+        Event::JoyAxisMotion {
+            timestamp: 0,
+            which,
+            axis_idx,
+            value,
+        }
+    }
+
+    fn axis_input(value: i16) -> Option<Input> {
+        Some(Input::ControllerAxis { value })
+    }
+
+    #[test]
+    fn game_controllers_count_once() {
+        let known = known();
+        let button = Event::ControllerButtonDown {
+            timestamp: 0,
+            which: PAD,
+            button: Button::A,
+        };
+        assert_eq!(known.input(&button), Some(Input::ControllerButtonDown));
+        let joy_button = Event::JoyButtonDown {
+            timestamp: 0,
+            which: PAD,
+            button_idx: 0,
+        };
+        assert_eq!(known.input(&joy_button), None);
+        let trigger = Event::ControllerAxisMotion {
+            timestamp: 0,
+            which: PAD,
+            axis: Axis::TriggerLeft,
+            value: 20000,
+        };
+        assert_eq!(known.input(&trigger), axis_input(20000));
+        assert_eq!(known.input(&axis(PAD, 2, 20000)), None);
+        let hat = Event::JoyHatMotion {
+            timestamp: 0,
+            which: PAD,
+            hat_idx: 0,
+            state: HatState::Up,
+        };
+        assert_eq!(known.input(&hat), None);
+    }
+
+    #[test]
+    fn joystick_axes_count_from_rest() {
+        let known = known();
+        assert_eq!(known.input(&axis(STICK, 0, 0)), axis_input(0));
+        assert_eq!(known.input(&axis(STICK, 0, -12000)), axis_input(-12000));
+        // a trigger left alone isn't pushed all the way
+        assert_eq!(known.input(&axis(STICK, 1, i16::MIN)), axis_input(0));
+        assert_eq!(known.input(&axis(STICK, 1, 0)), axis_input(i16::MAX));
+        assert_eq!(known.input(&axis(STICK, 1, i16::MAX)), axis_input(i16::MAX));
+        // nothing known about it, so it's taken as centered
+        assert_eq!(known.input(&axis(STICK, 7, 5000)), axis_input(5000));
+        assert_eq!(known.input(&axis(9, 0, 5000)), axis_input(5000));
+    }
+
+    #[test]
+    fn joystick_buttons_and_hats() {
+        let known = known();
+        let button = Event::JoyButtonDown {
+            timestamp: 0,
+            which: STICK,
+            button_idx: 2,
+        };
+        assert_eq!(known.input(&button), Some(Input::ControllerButtonDown));
+        let hat = |state| Event::JoyHatMotion {
+            timestamp: 0,
+            which: STICK,
+            hat_idx: 0,
+            state,
+        };
+        assert_eq!(known.input(&hat(HatState::LeftDown)), Some(Input::ControllerButtonDown));
+        assert_eq!(known.input(&hat(HatState::Centered)), None);
+    }
+
+    #[test]
+    fn distance_from_rest_saturates() {
+        assert_eq!(from_rest(100, 0), 100);
+        assert_eq!(from_rest(i16::MIN, i16::MAX), i16::MIN);
+        assert_eq!(from_rest(i16::MAX, i16::MIN), i16::MAX);
+        assert_eq!(from_rest(-100, -100), 0);
+    }
+}
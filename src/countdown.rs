@@ -0,0 +1,75 @@
+use std::time::Duration;
+
+use sdl2::pixels::Color;
+use sdl2::rect::Rect;
+use sdl2::render::WindowCanvas;
+
+use crate::text::{Align, GlyphAtlas, Label};
+
+const BAR_HEIGHT: u32 = 6;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Style {
+    /// Just the number.
+    Digits,
+    /// Each number fades out over its second.
+    Fade,
+    /// The number over a bar that shrinks as the countdown runs out.
+    Bar,
+}
+
+impl Style {
+    pub const ALL: [Style; 3] = [Style::Digits, Style::Fade, Style::Bar];
+
+    pub fn key(&self) -> &'static str {
+        match self {
+            Style::Digits => "digits",
+            Style::Fade => "fade",
+            Style::Bar => "bar",
+        }
+    }
+
+    pub fn from_key(key: &str) -> Option<Style> {
+        Style::ALL.into_iter().find(|s| s.key() == key)
+    }
+}
+
+/// The number on screen with `left` to go, 3, 2, 1 for a three second countdown.
+pub fn digit(left: Duration) -> u64 {
+    (left.as_secs_f64().ceil() as u64).max(1)
+}
+
+/// Draws the countdown in the middle of the window.
+pub fn draw(
+    canvas: &mut WindowCanvas,
+    atlas: &mut GlyphAtlas,
+    label: &mut Label,
+    style: Style,
+    (left, total): (Duration, Duration),
+    color: Color,
+    (width, height): (u32, u32),
+) -> Result<(), String> {
+    let digit = digit(left);
+    atlas.layout(label, &digit.to_string(), None)?;
+    if style == Style::Fade {
+        // how much of this number's second is left
+        let fraction = (left.as_secs_f64() - (digit - 1) as f64).clamp(0.0, 1.0);
+        atlas.set_alpha((fraction * 255.0) as u8);
+    }
+    let y = (height as i32 - atlas.height(label)) / 2;
+    let bottom = atlas.draw(canvas, label, Align::Center(width), y);
+    atlas.set_alpha(255);
+    let bottom = bottom?;
+
+    if style == Style::Bar && !total.is_zero() {
+        let full = width / 3;
+        let bar = (full as f64 * left.as_secs_f64() / total.as_secs_f64()) as u32;
+        if bar > 0 {
+            let old_color = canvas.draw_color();
+            canvas.set_draw_color(color);
+            canvas.fill_rect(Rect::new((width - bar) as i32 / 2, bottom, bar, BAR_HEIGHT))?;
+            canvas.set_draw_color(old_color);
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,132 @@
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+/// Everything that can end the program early, by the subsystem that failed.
+#[derive(Debug)]
+pub enum Error {
+    /// Bad config file, environment variable or flag.
+    Config(String),
+    /// SDL or one of its subsystems failed to start.
+    Init(&'static str, String),
+    Window(String),
+    Renderer(String),
+    Font(String),
+    /// Drawing something failed mid-run.
+    Render(&'static str, String),
+    Io(String, std::io::Error),
+    /// The `--headless` timeline doesn't parse.
+    Timeline(String),
+    /// None of the input devices could be read.
+    Evdev(String),
+}
+
+impl Error {
+    /// Each kind gets its own code so scripts can tell them apart.
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            Error::Config(..) => 2,
+            Error::Init(..) => 3,
+            Error::Window(..) => 4,
+            Error::Renderer(..) => 5,
+            Error::Font(..) => 6,
+            Error::Render(..) => 7,
+            Error::Io(..) => 8,
+            Error::Timeline(..) => 9,
+            Error::Evdev(..) => 10,
+        }
+    }
+
+    pub fn title(&self) -> &'static str {
+        match self {
+            Error::Config(..) => "Invalid configuration",
+            Error::Init(..) => "Failed to initialize",
+            Error::Window(..) => "Failed to create the window",
+            Error::Renderer(..) => "Failed to create a renderer",
+            Error::Font(..) => "Failed to load a font",
+            Error::Render(..) => "Failed to draw",
+            Error::Io(..) => "Failed to read or write a file",
+            Error::Timeline(..) => "Invalid timeline",
+            Error::Evdev(..) => "Failed to read the input devices",
+        }
+    }
+}
+
+impl std::fmt::Display for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Error::Config(msg) | Error::Window(msg) | Error::Renderer(msg) | Error::Font(msg) | Error::Timeline(msg) | Error::Evdev(msg) => {
+                write!(f, "{}: {}", self.title(), msg)
+            }
+            Error::Init(what, msg) => write!(f, "Failed to initialize {}: {}", what, msg),
+            Error::Render(what, msg) => write!(f, "Failed to draw {}: {}", what, msg),
+            Error::Io(context, e) => write!(f, "{}: {}", context, e),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Io(_, e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+/// Where fatal errors get shown.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Sink {
+    /// A message box, or stderr when one can't be shown (no display, no GTK).
+    MessageBox,
+    Stderr,
+    /// Appended to a file, or stderr when that can't be written.
+    File(PathBuf),
+}
+
+impl Sink {
+    pub fn key(&self) -> String {
+        match self {
+            Sink::MessageBox => "msgbox".to_string(),
+            Sink::Stderr => "stderr".to_string(),
+            Sink::File(path) => path.display().to_string(),
+        }
+    }
+
+    /// `msgbox`, `stderr`, or anything else as a file path.
+    pub fn from_key(key: &str) -> Option<Sink> {
+        match key {
+            "" => None,
+            "msgbox" => Some(Sink::MessageBox),
+            "stderr" => Some(Sink::Stderr),
+            path => Some(Sink::File(PathBuf::from(path))),
+        }
+    }
+
+    pub fn report(&self, error: &Error) {
+        let failed = match self {
+            Sink::MessageBox => msgbox::create(error.title(), &error.to_string(), msgbox::IconType::Error)
+                .err()
+                .map(|e| e.to_string()),
+            Sink::Stderr => {
+                eprintln!("{}", error);
+                return;
+            }
+            Sink::File(path) => append(path, error)
+                .err()
+                .map(|e| format!("{}: {}", path.display(), e)),
+        };
+        if let Some(why) = failed {
+            eprintln!("Couldn't report the error through {}: {}", self.key(), why);
+            eprintln!("{}", error);
+        }
+    }
+}
+
+fn append(path: &Path, error: &Error) -> std::io::Result<()> {
+    let secs = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs();
+    let mut file = std::fs::OpenOptions::new().create(true).append(true).open(path)?;
+    writeln!(file, "{} exit={} {}", secs, error.exit_code(), error)
+}
@@ -0,0 +1,619 @@
+use std::fs::File;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, Instant};
+
+use crate::error::Error;
+use crate::forensics::{self, Forensics};
+use crate::headless::Outcome;
+use crate::json;
+use crate::log;
+use crate::session::{Input, ManualClock, Phase, Rules, Session, Transition};
+
+pub const DEV_INPUT: &str = "/dev/input";
+
+const EV_SYN: u16 = 0x00;
+const EV_KEY: u16 = 0x01;
+const EV_REL: u16 = 0x02;
+const EV_ABS: u16 = 0x03;
+
+const SYN_REPORT: u16 = 0;
+const SYN_DROPPED: u16 = 3;
+
+const REL_X: u16 = 0x00;
+const REL_Y: u16 = 0x01;
+const REL_HWHEEL: u16 = 0x06;
+const REL_WHEEL: u16 = 0x08;
+
+const ABS_Z: u16 = 0x02;
+const ABS_RZ: u16 = 0x05;
+const ABS_THROTTLE: u16 = 0x06;
+const ABS_BRAKE: u16 = 0x0a;
+const ABS_HAT0X: u16 = 0x10;
+const ABS_HAT3Y: u16 = 0x17;
+
+const BTN_LEFT: u16 = 0x110;
+const BTN_TASK: u16 = 0x117;
+const BTN_JOYSTICK: u16 = 0x120;
+const BTN_THUMBR: u16 = 0x13e;
+const BTN_TOOL_PEN: u16 = 0x140;
+const BTN_TOUCH: u16 = 0x14a;
+const BTN_TOOL_QUADTAP: u16 = 0x14f;
+const BTN_DPAD_UP: u16 = 0x220;
+const BTN_DPAD_RIGHT: u16 = 0x223;
+const BTN_TRIGGER_HAPPY1: u16 = 0x2c0;
+const BTN_TRIGGER_HAPPY40: u16 = 0x2e7;
+
+#[cfg(target_os = "linux")]
+type RawEvent = libc::input_event;
+
+/// Stand-in for `struct input_event` where there are no devices to read it from.
+#[cfg(not(target_os = "linux"))]
+#[repr(C)]
+struct RawEvent {
+    type_: u16,
+    code: u16,
+    value: i32,
+}
+
+const EVENT_SIZE: usize = std::mem::size_of::<RawEvent>();
+/// ENODEV, what reading a device that was unplugged fails with.
+const NO_DEVICE: i32 = 19;
+
+/// How often the directory is looked at again for devices that were plugged in.
+const RESCAN: Duration = Duration::from_secs(1);
+/// How often `run` reads the devices.
+const POLL: Duration = Duration::from_millis(10);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Kind {
+    /// Has joystick or gamepad buttons, axes count as sticks and triggers.
+    Controller,
+    /// Touchscreen, touchpad or tablet.
+    Touch,
+    Other,
+}
+
+/// Input from one of the devices, ready for the session.
+#[derive(Debug, Clone)]
+pub struct Activity {
+    pub input: Input,
+    /// The key or button, for presses.
+    code: Option<u16>,
+    device: Rc<str>,
+}
+
+impl Activity {
+    /// What the result screen says about it.
+    pub fn describe(&self) -> String {
+        let what = match (self.input, self.code) {
+            (Input::KeyDown, Some(code)) => format!("key code {}", code),
+            (Input::MouseButtonDown, Some(code)) => match code {
+                0x110 => "left mouse button".to_string(),
+                0x111 => "right mouse button".to_string(),
+                0x112 => "middle mouse button".to_string(),
+                _ => format!("mouse button {}", code - BTN_LEFT + 1),
+            },
+            (Input::ControllerButtonDown, Some(code)) => format!("controller button {:#x}", code),
+            (Input::MouseMotion { dx, dy }, _) => format!("mouse moved by {}, {}", dx, dy),
+            (Input::MouseWheel { x, y }, _) => format!("mouse wheel {}", forensics::wheel_direction(x, y)),
+            (Input::ControllerAxis { value }, _) => format!("controller axis at {}", value),
+            (Input::DeviceChanged, _) => "plugged in or removed".to_string(),
+            (Input::Touch, _) => "touch".to_string(),
+            (input, _) => format!("{:?}", input),
+        };
+        format!("{} on {}", what, self.device)
+    }
+}
+
+struct Device {
+    path: PathBuf,
+    file: File,
+    /// Name and path, for the log and the result screen.
+    label: Rc<str>,
+    kind: Kind,
+    /// Raw range of each stick and trigger, by axis code.
+    ranges: [Option<(i32, i32)>; ABS_BRAKE as usize + 1],
+    /// Movement since the last report, events only make sense together.
+    motion: (i32, i32),
+    wheel: (i32, i32),
+}
+
+impl Device {
+    fn open(path: &Path) -> std::io::Result<Device> {
+        let file = open_device(path)?;
+        let node = path.file_name().unwrap_or_default().to_string_lossy();
+        let sysfs = Path::new("/sys/class/input").join(&*node).join("device");
+        let name = std::fs::read_to_string(sysfs.join("name")).unwrap_or_default();
+        let name = match name.trim() {
+            "" => "unknown device",
+            name => name,
+        };
+        let keys = capabilities(&sysfs, "key");
+        let kind = if has(&keys, BTN_TOUCH) {
+            Kind::Touch
+        } else if (BTN_JOYSTICK..=BTN_THUMBR).any(|b| has(&keys, b)) {
+            Kind::Controller
+        } else {
+            Kind::Other
+        };
+        let mut ranges = [None; ABS_BRAKE as usize + 1];
+        if kind == Kind::Controller {
+            let axes = capabilities(&sysfs, "abs");
+            for (code, range) in ranges.iter_mut().enumerate() {
+                if has(&axes, code as u16) {
+                    *range = abs_range(&file, code as u16);
+                }
+            }
+        }
+        Ok(Device {
+            path: path.to_path_buf(),
+            file,
+            label: format!("{} ({})", name, path.display()).into(),
+            kind,
+            ranges,
+            motion: (0, 0),
+            wheel: (0, 0),
+        })
+    }
+
+    /// Reads everything that's waiting. Returns `false` once the device is gone.
+    fn read(&mut self, out: &mut Vec<Activity>) -> bool {
+        let mut buf = [0u8; EVENT_SIZE * 64];
+        loop {
+            match self.file.read(&mut buf) {
+                Ok(0) => return true,
+                Ok(n) => {
+                    for chunk in buf[..n].chunks_exact(EVENT_SIZE) {
+                        // SAFETY: the chunk is exactly one event as the kernel wrote it, the struct is only
+                        // integers so any bytes are valid, and read_unaligned doesn't need the buffer aligned
+                        let event: RawEvent = unsafe { std::ptr::read_unaligned(chunk.as_ptr().cast()) };
+                        self.translate(event.type_, event.code, event.value, out);
+                    }
+                }
+                Err(e) if e.kind() == std::io::ErrorKind::WouldBlock => return true,
+                Err(e) if e.kind() == std::io::ErrorKind::Interrupted => {}
+                Err(e) => {
+                    if e.raw_os_error() != Some(NO_DEVICE) {
+                        log::warn!("evdev", "failed to read {}, not reading it anymore: {}", self.label, e);
+                    }
+                    return false;
+                }
+            }
+        }
+    }
+
+    fn translate(&mut self, kind: u16, code: u16, value: i32, out: &mut Vec<Activity>) {
+        match (kind, code) {
+            (EV_SYN, SYN_REPORT) => {
+                let (dx, dy) = std::mem::take(&mut self.motion);
+                if (dx, dy) != (0, 0) {
+                    out.push(self.activity(Input::MouseMotion { dx, dy }, None));
+                }
+                let (x, y) = std::mem::take(&mut self.wheel);
+                if (x, y) != (0, 0) {
+                    out.push(self.activity(Input::MouseWheel { x, y }, None));
+                }
+            }
+            // the kernel's buffer overflowed, what's been collected is incomplete
+            (EV_SYN, SYN_DROPPED) => {
+                self.motion = (0, 0);
+                self.wheel = (0, 0);
+            }
+            // 0 is a release and 2 a key repeat
+            (EV_KEY, _) if value == 1 => {
+                if let Some(input) = button(code) {
+                    out.push(self.activity(input, Some(code)));
+                }
+            }
+            (EV_REL, REL_X) => self.motion.0 += value,
+            (EV_REL, REL_Y) => self.motion.1 += value,
+            (EV_REL, REL_HWHEEL) => self.wheel.0 += value,
+            (EV_REL, REL_WHEEL) => self.wheel.1 += value,
+            (EV_ABS, ABS_HAT0X..=ABS_HAT3Y) if self.kind == Kind::Controller && value != 0 => {
+                out.push(self.activity(Input::ControllerButtonDown, Some(code)));
+            }
+            (EV_ABS, _) if self.kind == Kind::Controller => {
+                if let Some(range) = self.ranges.get(code as usize).copied().flatten() {
+                    let trigger = matches!(code, ABS_Z | ABS_RZ | ABS_THROTTLE..=ABS_BRAKE) && range.0 >= 0;
+                    let value = scale(value, range, trigger);
+                    out.push(self.activity(Input::ControllerAxis { value }, None));
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn activity(&self, input: Input, code: Option<u16>) -> Activity {
+        Activity {
+            input,
+            code,
+            device: self.label.clone(),
+        }
+    }
+}
+
+/// Reads the event devices directly, so input is seen wherever it goes, not only when it
+/// goes to our window.
+pub struct Monitor {
+    dir: PathBuf,
+    devices: Vec<Device>,
+    scanned: Instant,
+}
+
+impl Monitor {
+    /// Opens every event device in `dir` that can be read, and fails saying why when none can.
+    pub fn open(dir: &Path) -> Result<Monitor, Error> {
+        if !cfg!(target_os = "linux") {
+            return Err(Error::Evdev("reading input devices directly only works on Linux".to_string()));
+        }
+        let nodes = event_nodes(dir).map_err(|e| Error::Evdev(format!("can't list {}: {}", dir.display(), e)))?;
+        let mut devices = Vec::new();
+        let mut denied = Vec::new();
+        for path in nodes {
+            match Device::open(&path) {
+                Ok(device) => {
+                    log::info!("evdev", "reading {}", device.label);
+                    devices.push(device);
+                }
+                Err(e) if e.kind() == std::io::ErrorKind::PermissionDenied => denied.push(path.display().to_string()),
+                Err(e) => log::warn!("evdev", "skipping {}: {}", path.display(), e),
+            }
+        }
+        if devices.is_empty() {
+            return Err(Error::Evdev(if denied.is_empty() {
+                format!("there are no input devices in {}", dir.display())
+            } else {
+                format!(
+                    "no permission to read {}. Add yourself to the group that owns them, usually `input` \
+                     (sudo usermod -aG input $USER), then log in again",
+                    denied.join(", ")
+                )
+            }));
+        }
+        if !denied.is_empty() {
+            log::warn!("evdev", "no permission to read {}, input from them won't be seen", denied.join(", "));
+        }
+        Ok(Monitor {
+            dir: dir.to_path_buf(),
+            devices,
+            scanned: Instant::now(),
+        })
+    }
+
+    /// Queues up everything that came in since the last call. Controllers being plugged in
+    /// or removed count, the same as they do through SDL.
+    pub fn poll(&mut self, now: Instant, out: &mut Vec<Activity>) {
+        let mut gone = Vec::new();
+        for (i, device) in self.devices.iter_mut().enumerate() {
+            if !device.read(out) {
+                gone.push(i);
+            }
+        }
+        for i in gone.into_iter().rev() {
+            let device = self.devices.remove(i);
+            log::info!("evdev", "{} went away", device.label);
+            if device.kind == Kind::Controller {
+                out.push(device.activity(Input::DeviceChanged, None));
+            }
+        }
+        if now - self.scanned >= RESCAN {
+            self.scanned = now;
+            self.scan(out);
+        }
+    }
+
+    fn scan(&mut self, out: &mut Vec<Activity>) {
+        let nodes = match event_nodes(&self.dir) {
+            Ok(nodes) => nodes,
+            Err(e) => {
+                log::debug!("evdev", "can't list {}: {}", self.dir.display(), e);
+                return;
+            }
+        };
+        for path in nodes {
+            if self.devices.iter().any(|d| d.path == path) {
+                continue;
+            }
+            // udev may not have set the permissions yet, it gets tried again next time
+            match Device::open(&path) {
+                Ok(device) => {
+                    log::info!("evdev", "reading {}", device.label);
+                    if device.kind == Kind::Controller {
+                        out.push(device.activity(Input::DeviceChanged, None));
+                    }
+                    self.devices.push(device);
+                }
+                Err(e) => log::debug!("evdev", "can't open {}: {}", path.display(), e),
+            }
+        }
+    }
+}
+
+/// Tracks a run from the input devices alone, without a window, until it fails or a signal
+/// comes in, then prints it as JSON the way `--headless` does.
+pub fn run(dir: &Path, rules: Rules, shutdown: &AtomicBool) -> Result<(), Error> {
+    let mut monitor = Monitor::open(dir)?;
+    let origin = Instant::now();
+    let clock = ManualClock::new(origin);
+    let mut session = Session::with_clock(clock.clone()).with_rules(rules);
+    let mut forensics = Forensics::default();
+    let mut started_at = Duration::ZERO;
+    let mut activity = Vec::new();
+    log::info!("evdev", "press a key or a mouse button to start");
+
+    while !shutdown.load(Ordering::SeqCst) && session.phase() != Phase::Failed {
+        let now = Instant::now();
+        clock.set(now);
+        activity.clear();
+        monitor.poll(now, &mut activity);
+        for a in &activity {
+            let before = session.phase();
+            let transition = session.handle(a.input);
+            let detail = (transition == Some(Transition::Failed)).then(|| a.describe());
+            forensics.observe(a.input, transition, session.elapsed(), detail);
+            log::debug!("input", "{} phase={:?} result={:?}", a.describe(), before, transition);
+            if let Some(t) = transition {
+                log::info!("session", "{:?} phase={:?}", t, session.phase());
+            }
+            if transition == Some(Transition::Activated) {
+                started_at = now - origin;
+            }
+        }
+        if let Some(t) = session.tick() {
+            log::info!("session", "{:?} phase={:?}", t, session.phase());
+            if t == Transition::Activated {
+                // the countdown ran out a little before this
+                started_at = (now - origin).saturating_sub(session.elapsed());
+            }
+        }
+        std::thread::sleep(POLL);
+    }
+
+    let outcome = Outcome {
+        started_at,
+        inactive: session.elapsed(),
+        paused: session.paused(),
+        reasons: session.reasons(),
+        failed: session.phase() == Phase::Failed,
+        background: false,
+        idle_mismatch: false,
+    };
+    println!(
+        "{}",
+        json::Value::object(vec![("run", outcome.to_json()), ("forensics", forensics.to_json())])
+    );
+    Ok(())
+}
+
+/// What a key press means, if anything.
+fn button(code: u16) -> Option<Input> {
+    match code {
+        BTN_TOUCH => Some(Input::Touch),
+        // which tool is near a tablet or touchpad, not a press
+        BTN_TOOL_PEN..=BTN_TOOL_QUADTAP => None,
+        BTN_LEFT..=BTN_TASK => Some(Input::MouseButtonDown),
+        BTN_JOYSTICK..=BTN_THUMBR | BTN_DPAD_UP..=BTN_DPAD_RIGHT | BTN_TRIGGER_HAPPY1..=BTN_TRIGGER_HAPPY40 => {
+            Some(Input::ControllerButtonDown)
+        }
+        _ => Some(Input::KeyDown),
+    }
+}
+
+/// Puts a raw axis position on SDL's scale, -32768 to 32767 with sticks at rest in the
+/// middle, or 0 to 32767 for triggers.
+fn scale(value: i32, (min, max): (i32, i32), trigger: bool) -> i16 {
+    if max <= min {
+        return 0;
+    }
+    let fraction = (value.clamp(min, max) - min) as f64 / (max - min) as f64;
+    let scaled = if trigger { fraction * 32767.0 } else { fraction * 65535.0 - 32768.0 };
+    scaled.round() as i16
+}
+
+/// `eventN` files in `dir`, in order.
+fn event_nodes(dir: &Path) -> std::io::Result<Vec<PathBuf>> {
+    let mut nodes: Vec<(u32, PathBuf)> = Vec::new();
+    for entry in std::fs::read_dir(dir)? {
+        let entry = entry?;
+        let name = entry.file_name();
+        if let Some(n) = name.to_str().and_then(|n| n.strip_prefix("event")).and_then(|n| n.parse().ok()) {
+            nodes.push((n, entry.path()));
+        }
+    }
+    nodes.sort();
+    Ok(nodes.into_iter().map(|(_, path)| path).collect())
+}
+
+/// One of the device's capability bitmasks from sysfs, lowest word first.
+fn capabilities(sysfs: &Path, which: &str) -> Vec<u64> {
+    let text = std::fs::read_to_string(sysfs.join("capabilities").join(which)).unwrap_or_default();
+    text.split_whitespace()
+        .rev()
+        .map(|word| u64::from_str_radix(word, 16).unwrap_or(0))
+        .collect()
+}
+
+fn has(words: &[u64], bit: u16) -> bool {
+    // the kernel prints them a long at a time
+    let bits = 8 * std::mem::size_of::<std::os::raw::c_ulong>();
+    let bit = bit as usize;
+    words.get(bit / bits).is_some_and(|word| word >> (bit % bits) & 1 == 1)
+}
+
+#[cfg(target_os = "linux")]
+fn open_device(path: &Path) -> std::io::Result<File> {
+    use std::os::unix::fs::OpenOptionsExt;
+    std::fs::OpenOptions::new().read(true).custom_flags(libc::O_NONBLOCK).open(path)
+}
+
+#[cfg(not(target_os = "linux"))]
+fn open_device(_: &Path) -> std::io::Result<File> {
+    Err(std::io::ErrorKind::Unsupported.into())
+}
+
+/// Minimum and maximum of an axis, through EVIOCGABS.
+#[cfg(target_os = "linux")]
+fn abs_range(file: &File, code: u16) -> Option<(i32, i32)> {
+    use std::os::unix::io::AsRawFd;
+    // SAFETY: input_absinfo is only integers, all zeroes is a valid one
+    let mut info: libc::input_absinfo = unsafe { std::mem::zeroed() };
+    // SAFETY: EVIOCGABS writes one input_absinfo through the pointer, which is valid for the whole call
+    let result = unsafe { libc::ioctl(file.as_raw_fd(), eviocgabs(code), &mut info as *mut libc::input_absinfo) };
+    (result == 0).then_some((info.minimum, info.maximum))
+}
+
+/// `EVIOCGABS(code)`, which is `_IOR('E', 0x40 + code, struct input_absinfo)`.
+#[cfg(target_os = "linux")]
+fn eviocgabs(code: u16) -> libc::Ioctl {
+    // these have a bit more for the direction and a bit less for the size
+    #[cfg(any(
+        target_arch = "mips",
+        target_arch = "mips64",
+        target_arch = "powerpc",
+        target_arch = "powerpc64",
+        target_arch = "sparc",
+        target_arch = "sparc64"
+    ))]
+    const DIR_SHIFT: u32 = 29;
+    #[cfg(not(any(
+        target_arch = "mips",
+        target_arch = "mips64",
+        target_arch = "powerpc",
+        target_arch = "powerpc64",
+        target_arch = "sparc",
+        target_arch = "sparc64"
+    )))]
+    const DIR_SHIFT: u32 = 30;
+    const READ: u32 = 2;
+    let size = std::mem::size_of::<libc::input_absinfo>() as u32;
+    let request = READ << DIR_SHIFT | size << 16 | u32::from(b'E') << 8 | (0x40 + u32::from(code));
+    request as libc::Ioctl
+}
+
+#[cfg(not(target_os = "linux"))]
+fn abs_range(_: &File, _: u16) -> Option<(i32, i32)> {
+    None
+}
+
+// the numbers are from <linux/input.h> on x86_64
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const KEY_A: u16 = 30;
+    const ABS_X: u16 = 0x00;
+    const BTN_SOUTH: u16 = 0x130;
+
+    /// A pad with a stick axis reporting 0 to 255 and a trigger reporting 0 to 1023.
+    fn device(kind: Kind) -> Device {
+        let mut ranges = [None; ABS_BRAKE as usize + 1];
+        ranges[ABS_X as usize] = Some((0, 255));
+        ranges[ABS_Z as usize] = Some((0, 1023));
+        Device {
+            path: PathBuf::from("/dev/input/event7"),
+            // never read, it only has to be some open file
+            file: File::open(std::env::current_exe().unwrap()).unwrap(),
+            label: "test pad (/dev/input/event7)".into(),
+            kind,
+            ranges,
+            motion: (0, 0),
+            wheel: (0, 0),
+        }
+    }
+
+    fn translate(device: &mut Device, events: &[(u16, u16, i32)]) -> Vec<Input> {
+        let mut out = Vec::new();
+        for &(kind, code, value) in events {
+            device.translate(kind, code, value, &mut out);
+        }
+        out.into_iter().map(|a| a.input).collect()
+    }
+
+    #[cfg(all(target_os = "linux", target_arch = "x86_64"))]
+    #[test]
+    fn layouts_match_the_kernel() {
+        assert_eq!(EVENT_SIZE, 24);
+        assert_eq!(eviocgabs(0) as u64, 0x8018_4540);
+        assert_eq!(eviocgabs(ABS_HAT3Y) as u64, 0x8018_4557);
+    }
+
+    #[test]
+    fn keys_and_buttons() {
+        assert_eq!(button(KEY_A), Some(Input::KeyDown));
+        assert_eq!(button(BTN_LEFT), Some(Input::MouseButtonDown));
+        assert_eq!(button(BTN_TASK), Some(Input::MouseButtonDown));
+        assert_eq!(button(BTN_TOUCH), Some(Input::Touch));
+        assert_eq!(button(BTN_TOOL_PEN), None);
+        assert_eq!(button(BTN_TOOL_QUADTAP), None);
+        for code in [BTN_JOYSTICK, BTN_SOUTH, BTN_THUMBR, BTN_DPAD_UP, BTN_TRIGGER_HAPPY40] {
+            assert_eq!(button(code), Some(Input::ControllerButtonDown), "{:#x}", code);
+        }
+    }
+
+    #[test]
+    fn only_presses_count() {
+        let mut device = device(Kind::Other);
+        let presses = translate(&mut device, &[(EV_KEY, KEY_A, 1), (EV_KEY, KEY_A, 2), (EV_KEY, KEY_A, 0)]);
+        assert_eq!(presses, [Input::KeyDown]);
+    }
+
+    #[test]
+    fn sticks_and_triggers_scale() {
+        assert_eq!(scale(0, (0, 255), false), i16::MIN);
+        assert_eq!(scale(128, (0, 255), false), 128);
+        assert_eq!(scale(255, (0, 255), false), i16::MAX);
+        assert_eq!(scale(-32768, (-32768, 32767), false), i16::MIN);
+        assert_eq!(scale(0, (-32768, 32767), false), 0);
+        assert_eq!(scale(0, (0, 1023), true), 0);
+        assert_eq!(scale(512, (0, 1023), true), 16400);
+        assert_eq!(scale(1023, (0, 1023), true), i16::MAX);
+        // out of range and broken ranges
+        assert_eq!(scale(2000, (0, 1023), true), i16::MAX);
+        assert_eq!(scale(5, (10, 10), false), 0);
+    }
+
+    #[test]
+    fn axes_only_count_on_controllers() {
+        let events = [(EV_ABS, ABS_X, 255), (EV_ABS, ABS_Z, 1023), (EV_ABS, ABS_HAT0X, -1), (EV_ABS, ABS_HAT0X, 0)];
+        assert_eq!(
+            translate(&mut device(Kind::Controller), &events),
+            [
+                Input::ControllerAxis { value: i16::MAX },
+                Input::ControllerAxis { value: i16::MAX },
+                Input::ControllerButtonDown,
+            ]
+        );
+        assert!(translate(&mut device(Kind::Other), &events).is_empty());
+        // no range, nothing to scale it with
+        assert!(translate(&mut device(Kind::Controller), &[(EV_ABS, ABS_RZ, 100)]).is_empty());
+    }
+
+    #[test]
+    fn relative_motion_adds_up_until_the_report() {
+        let mut device = device(Kind::Other);
+        let moved = translate(
+            &mut device,
+            &[(EV_REL, REL_X, 3), (EV_REL, REL_Y, -2), (EV_REL, REL_X, 1), (EV_REL, REL_WHEEL, -1)],
+        );
+        assert!(moved.is_empty());
+        assert_eq!(
+            translate(&mut device, &[(EV_SYN, SYN_REPORT, 0)]),
+            [Input::MouseMotion { dx: 4, dy: -2 }, Input::MouseWheel { x: 0, y: -1 }]
+        );
+        // nothing since the last report
+        assert!(translate(&mut device, &[(EV_SYN, SYN_REPORT, 0)]).is_empty());
+        // back and forth within one report adds up to nothing
+        let back = translate(&mut device, &[(EV_REL, REL_X, 5), (EV_REL, REL_X, -5), (EV_SYN, SYN_REPORT, 0)]);
+        assert!(back.is_empty());
+    }
+
+    #[test]
+    fn dropped_events_throw_away_the_partial_report() {
+        let mut device = device(Kind::Other);
+        let events = [(EV_REL, REL_X, 7), (EV_REL, REL_HWHEEL, 1), (EV_SYN, SYN_DROPPED, 0), (EV_SYN, SYN_REPORT, 0)];
+        assert!(translate(&mut device, &events).is_empty());
+    }
+}
@@ -0,0 +1,213 @@
+use std::time::Duration;
+
+use crate::json;
+use crate::session::{Input, Reason, Transition};
+
+/// What ended a run.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Offender {
+    /// Into the run, same as what the result screen shows as the inactive time.
+    pub at: Duration,
+    pub reason: Reason,
+    /// What the front end knew about it (which key, where the cursor was...).
+    pub detail: String,
+}
+
+/// What ended the last run and what else came in while its result was up.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Forensics {
+    first: Option<Offender>,
+    counts: [u32; Reason::ALL.len()],
+}
+
+impl Forensics {
+    /// Call with every input the session handled. Only what counted against the run or got tallied
+    /// after it failed is counted, not what the filter dropped.
+    pub fn observe(&mut self, input: Input, transition: Option<Transition>, elapsed: Duration, detail: Option<String>) {
+        let reason = Reason::from(input);
+        match transition {
+            Some(Transition::CountdownStarted | Transition::Activated) => *self = Forensics::default(),
+            Some(Transition::Failed) => {
+                let detail = detail.unwrap_or_else(|| describe(input));
+                self.first = Some(Offender { at: elapsed, reason, detail });
+                self.count(reason);
+            }
+            Some(Transition::Tallied) => self.count(reason),
+            _ => {}
+        }
+    }
+
+    /// Call when the session was told the window lost focus.
+    pub fn observe_focus_lost(&mut self, transition: Option<Transition>, elapsed: Duration, detail: String) {
+        if transition == Some(Transition::Failed) {
+            self.first = Some(Offender {
+                at: elapsed,
+                reason: Reason::FocusLost,
+                detail,
+            });
+            self.count(Reason::FocusLost);
+        }
+    }
+
+    /// Call with what every tick did, a run only fails there when it went live without focus.
+    pub fn observe_tick(&mut self, transition: Option<Transition>, elapsed: Duration) {
+        self.observe_focus_lost(transition, elapsed, "window didn't have focus when the run started".to_string());
+    }
+
+    fn count(&mut self, reason: Reason) {
+        if let Some(i) = Reason::ALL.iter().position(|r| *r == reason) {
+            self.counts[i] += 1;
+        }
+    }
+
+    pub fn set_detail(&mut self, detail: String) {
+        if let Some(first) = self.first.as_mut() {
+            first.detail = detail;
+        }
+    }
+
+    /// Kinds that showed up at least once.
+    pub fn counts(&self) -> impl Iterator<Item = (Reason, u32)> + '_ {
+        Reason::ALL.into_iter().zip(self.counts).filter(|(_, n)| *n > 0)
+    }
+
+    /// The extra lines under the result.
+    pub fn lines(&self) -> Vec<String> {
+        let first = match &self.first {
+            Some(f) => f,
+            None => return Vec::new(),
+        };
+        let counts: Vec<String> = self.counts().map(|(r, n)| format!("{} {}", n, r.key())).collect();
+        vec![
+            format!("First: {} at {:.3} s", first.detail, first.at.as_secs_f64()),
+            format!("Inputs from then on: {}", counts.join(", ")),
+        ]
+    }
+
+    pub fn to_json(&self) -> json::Value {
+        let first = self.first.as_ref().map(|f| {
+            json::Value::object(vec![
+                ("at", f.at.as_secs_f64().into()),
+                ("kind", f.reason.key().into()),
+                ("detail", f.detail.clone().into()),
+            ])
+        });
+        let counts: Vec<(&str, json::Value)> = self.counts().map(|(r, n)| (r.key(), n.into())).collect();
+        json::Value::object(vec![
+            ("first", first.into()),
+            ("counts", json::Value::object(counts)),
+        ])
+    }
+}
+
+/// Without a front end to ask, this is all there is to say.
+fn describe(input: Input) -> String {
+    match input {
+        Input::MouseMotion { dx, dy } => format!("mouse moved by {}, {}", dx, dy),
+        Input::MouseWheel { x, y } => format!("mouse wheel {}", wheel_direction(x, y)),
+        Input::ControllerAxis { value } => format!("controller axis at {}", value),
+        _ => Reason::from(input).description().to_string(),
+    }
+}
+
+pub fn wheel_direction(x: i32, y: i32) -> &'static str {
+    match (x.signum(), y.signum()) {
+        (_, 1) => "up",
+        (_, -1) => "down",
+        (1, _) => "right",
+        (-1, _) => "left",
+        _ => "nowhere",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sensitivity::Sensitivity;
+    use crate::session::{FocusPolicy, ManualClock, Rules, Session};
+    use std::time::Instant;
+
+    /// A session that goes live on the first key, with a 10 pixel dead zone.
+    fn live(focus: FocusPolicy) -> (Session<ManualClock>, ManualClock, Instant) {
+        let start = Instant::now();
+        let clock = ManualClock::new(start);
+        let rules = Rules {
+            countdown: Duration::ZERO,
+            ignore_first_input: false,
+            focus,
+            sensitivity: Sensitivity {
+                motion_dead_zone: 10,
+                ..Sensitivity::default()
+            },
+            ..Rules::default()
+        };
+        let mut session = Session::with_clock(clock.clone()).with_rules(rules);
+        assert_eq!(session.handle(Input::KeyDown), Some(Transition::Activated));
+        (session, clock, start)
+    }
+
+    fn feed(session: &mut Session<ManualClock>, forensics: &mut Forensics, input: Input) {
+        let transition = session.handle(input);
+        forensics.observe(input, transition, session.elapsed(), None);
+    }
+
+    #[test]
+    fn counts_only_what_got_past_the_filter() {
+        let (mut session, clock, start) = live(FocusPolicy::Ignore);
+        let mut forensics = Forensics::default();
+        clock.set(start + Duration::from_millis(2500));
+        // inside the dead zone, the run keeps going
+        feed(&mut session, &mut forensics, Input::MouseMotion { dx: 6, dy: 0 });
+        assert_eq!(forensics.counts().count(), 0);
+        feed(&mut session, &mut forensics, Input::MouseButtonDown);
+        // still inside the dead zone, so it wasn't there for the result either
+        feed(&mut session, &mut forensics, Input::MouseMotion { dx: 2, dy: 0 });
+        feed(&mut session, &mut forensics, Input::KeyDown);
+        feed(&mut session, &mut forensics, Input::MouseMotion { dx: 5, dy: 0 });
+        feed(&mut session, &mut forensics, Input::KeyDown);
+        assert_eq!(
+            forensics.counts().collect::<Vec<_>>(),
+            [(Reason::Keyboard, 2), (Reason::MouseButton, 1), (Reason::MouseMotion, 1)]
+        );
+        assert_eq!(
+            forensics.lines(),
+            [
+                "First: Mouse button presses detected at 2.500 s",
+                "Inputs from then on: 2 keyboard, 1 mouse_button, 1 mouse_motion",
+            ]
+        );
+    }
+
+    #[test]
+    fn the_front_end_describes_the_offender() {
+        let (mut session, _clock, _start) = live(FocusPolicy::Ignore);
+        let mut forensics = Forensics::default();
+        let transition = session.handle(Input::MouseWheel { x: 0, y: -1 });
+        forensics.observe(Input::MouseWheel { x: 0, y: -1 }, transition, session.elapsed(), None);
+        assert_eq!(forensics.first.as_ref().map(|f| f.detail.as_str()), Some("mouse wheel down"));
+        forensics.set_detail("wheel on the left mouse".to_string());
+        assert_eq!(forensics.first.as_ref().map(|f| f.detail.as_str()), Some("wheel on the left mouse"));
+    }
+
+    #[test]
+    fn losing_focus_is_the_offender() {
+        let (mut session, _clock, _start) = live(FocusPolicy::Fail);
+        let mut forensics = Forensics::default();
+        let transition = session.focus_lost();
+        forensics.observe_focus_lost(transition, session.elapsed(), "alt-tabbed".to_string());
+        assert_eq!(forensics.first.as_ref().map(|f| f.reason), Some(Reason::FocusLost));
+        assert_eq!(forensics.counts().collect::<Vec<_>>(), [(Reason::FocusLost, 1)]);
+    }
+
+    #[test]
+    fn a_new_run_starts_clean() {
+        let (mut session, clock, start) = live(FocusPolicy::Ignore);
+        let mut forensics = Forensics::default();
+        feed(&mut session, &mut forensics, Input::KeyDown);
+        assert!(!forensics.lines().is_empty());
+        clock.set(start + Duration::from_secs(60));
+        while session.tick().is_some() {}
+        feed(&mut session, &mut forensics, Input::KeyDown);
+        assert_eq!(forensics, Forensics::default());
+    }
+}
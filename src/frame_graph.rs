@@ -0,0 +1,114 @@
+use std::time::Duration;
+
+use sdl2::pixels::Color;
+use sdl2::rect::Rect;
+use sdl2::render::{BlendMode, Canvas};
+use sdl2::video::Window;
+
+use crate::frame_stats::FrameStats;
+
+const GOOD: Color = Color::RGB(80, 200, 120);
+const SLOW: Color = Color::RGB(230, 200, 60);
+const SPIKE: Color = Color::RGB(230, 70, 60);
+const TARGET: Color = Color::RGB(100, 160, 255);
+const BACKDROP: Color = Color::RGBA(0, 0, 0, 160);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GraphMode {
+    /// A small graph of the last couple of seconds under the HUD text.
+    Compact,
+    /// Across the whole window.
+    Full,
+    Hidden,
+}
+
+impl GraphMode {
+    pub fn key(&self) -> &'static str {
+        match self {
+            GraphMode::Compact => "compact",
+            GraphMode::Full => "full",
+            GraphMode::Hidden => "hidden",
+        }
+    }
+
+    pub fn from_key(key: &str) -> Option<GraphMode> {
+        [GraphMode::Compact, GraphMode::Full, GraphMode::Hidden]
+            .into_iter()
+            .find(|m| m.key() == key)
+    }
+
+    /// What the hotkey switches to.
+    pub fn next(self) -> GraphMode {
+        match self {
+            GraphMode::Compact => GraphMode::Full,
+            GraphMode::Full => GraphMode::Hidden,
+            GraphMode::Hidden => GraphMode::Compact,
+        }
+    }
+
+    /// Where the graph goes when the HUD text ends at `top` in a window `width` wide.
+    pub fn area(&self, top: i32, width: u32) -> Option<Rect> {
+        match self {
+            GraphMode::Compact => Some(Rect::new(0, top, 240, 60)),
+            GraphMode::Full => Some(Rect::new(0, top, width.max(1), 160)),
+            GraphMode::Hidden => None,
+        }
+    }
+}
+
+/// Draws one bar per frame, newest on the right, two pixels per frame. Frames over
+/// `target` (the limiter's frame time, or the median when uncapped) by more than half
+/// are yellow, twice it or more red.
+pub fn draw(
+    canvas: &mut Canvas<Window>,
+    stats: &FrameStats,
+    target: Option<Duration>,
+    area: Rect,
+) -> Result<(), String> {
+    let count = (area.width() / 2) as usize;
+    let frames: Vec<Duration> = stats.recent().take(count).collect();
+    let reference = match target {
+        Some(t) => t,
+        None => median(&frames),
+    };
+    if reference.is_zero() {
+        return Ok(());
+    }
+    // twice the reference fits, anything longer sticks to the top
+    let scale = area.height() as f64 / (reference.as_secs_f64() * 2.0);
+
+    let old_color = canvas.draw_color();
+    let old_blend = canvas.blend_mode();
+    canvas.set_blend_mode(BlendMode::Blend);
+    canvas.set_draw_color(BACKDROP);
+    canvas.fill_rect(area)?;
+
+    let bottom = area.bottom();
+    for (i, frame) in frames.iter().enumerate() {
+        let height = ((frame.as_secs_f64() * scale) as u32).clamp(1, area.height());
+        let x = area.right() - 2 * (i as i32 + 1);
+        canvas.set_draw_color(if *frame >= reference * 2 {
+            SPIKE
+        } else if *frame > reference + reference / 2 {
+            SLOW
+        } else {
+            GOOD
+        });
+        canvas.fill_rect(Rect::new(x, bottom - height as i32, 2, height))?;
+    }
+    if target.is_some() {
+        let y = bottom - (reference.as_secs_f64() * scale) as i32;
+        canvas.set_draw_color(TARGET);
+        canvas.draw_line((area.left(), y), (area.right() - 1, y))?;
+    }
+
+    canvas.set_draw_color(old_color);
+    canvas.set_blend_mode(old_blend);
+    Ok(())
+}
+
+fn median(frames: &[Duration]) -> Duration {
+    let mut sorted = frames.to_vec();
+    sorted.sort_unstable();
+    sorted.get(sorted.len() / 2).copied().unwrap_or_default()
+}
@@ -0,0 +1,362 @@
+use std::time::{Duration, Instant};
+
+/// How long before the deadline the hybrid strategies stop sleeping and start spinning,
+/// covers the usual scheduler wake up latency.
+const SPIN_MARGIN: Duration = Duration::from_millis(2);
+
+pub trait Timer {
+    fn now(&self) -> Instant;
+    fn sleep(&self, duration: Duration);
+}
+
+pub struct SystemTimer;
+
+impl Timer for SystemTimer {
+    fn now(&self) -> Instant {
+        Instant::now()
+    }
+
+    fn sleep(&self, duration: Duration) {
+        std::thread::sleep(duration)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Strategy {
+    /// Sleep for whatever is left of the frame. Cheapest, but wakes up late.
+    Sleep,
+    /// Busy wait until the frame is over. Precise, burns a core.
+    Spin,
+    /// Sleep most of the way, spin the last couple of milliseconds.
+    Hybrid,
+    /// Like hybrid, but frames are scheduled on a fixed grid so waking up late
+    /// doesn't push every following frame back.
+    Deadline,
+}
+
+impl Strategy {
+    pub const ALL: [Strategy; 4] = [
+        Strategy::Sleep,
+        Strategy::Spin,
+        Strategy::Hybrid,
+        Strategy::Deadline,
+    ];
+
+    pub fn key(&self) -> &'static str {
+        match self {
+            Strategy::Sleep => "sleep",
+            Strategy::Spin => "spin",
+            Strategy::Hybrid => "hybrid",
+            Strategy::Deadline => "deadline",
+        }
+    }
+
+    pub fn from_key(key: &str) -> Option<Strategy> {
+        Strategy::ALL.into_iter().find(|s| s.key() == key)
+    }
+}
+
+/// What the last frame actually took.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct FrameTiming {
+    /// From the end of the previous wait to the end of this one.
+    pub frame_time: Duration,
+    /// How far past its target the wait ended.
+    pub late: Duration,
+    /// [`Strategy::Deadline`] fell too far behind and started a new schedule.
+    pub resynced: bool,
+}
+
+pub struct FrameLimiter<T: Timer = SystemTimer> {
+    timer: T,
+    strategy: Strategy,
+    /// `None` when uncapped.
+    frame_duration: Option<Duration>,
+    frame_start: Instant,
+    /// End of the current frame on the fixed grid, only used by [`Strategy::Deadline`].
+    deadline: Instant,
+}
+
+impl FrameLimiter<SystemTimer> {
+    /// `None` for no cap.
+    pub fn new(target_fps: Option<u32>, strategy: Strategy) -> Self {
+        FrameLimiter::with_timer(target_fps, strategy, SystemTimer)
+    }
+}
+
+impl<T: Timer> FrameLimiter<T> {
+    pub fn with_timer(target_fps: Option<u32>, strategy: Strategy, timer: T) -> Self {
+        let frame_duration = target_fps.map(|f| Duration::from_secs_f64(1.0 / f.max(1) as f64));
+        let now = timer.now();
+        FrameLimiter {
+            timer,
+            strategy,
+            frame_duration,
+            frame_start: now,
+            deadline: now + frame_duration.unwrap_or_default(),
+        }
+    }
+
+    /// What a frame is paced to, `None` when uncapped.
+    pub fn frame_duration(&self) -> Option<Duration> {
+        self.frame_duration
+    }
+
+    pub fn strategy(&self) -> Strategy {
+        self.strategy
+    }
+
+    /// Blocks until the current frame has used up its time, call once per frame after presenting.
+    pub fn wait(&mut self) -> FrameTiming {
+        let mut resynced = false;
+        let target = match self.frame_duration {
+            None => None,
+            Some(frame) if self.strategy == Strategy::Deadline => {
+                // more than a whole frame behind (a hitch, or the window got dragged), start a
+                // new grid from here instead of rushing out frames to catch up
+                if self.timer.now() > self.deadline + frame {
+                    self.deadline = self.timer.now();
+                    resynced = true;
+                }
+                Some(self.deadline)
+            }
+            Some(frame) => Some(self.frame_start + frame),
+        };
+        if let Some(target) = target {
+            self.wait_until(target);
+        }
+        let now = self.timer.now();
+        let timing = FrameTiming {
+            frame_time: now - self.frame_start,
+            late: target.map(|t| now.saturating_duration_since(t)).unwrap_or_default(),
+            resynced,
+        };
+        self.frame_start = now;
+        if let (Some(target), Some(frame)) = (target, self.frame_duration) {
+            self.deadline = target + frame;
+        }
+        timing
+    }
+
+    fn wait_until(&self, target: Instant) {
+        let now = self.timer.now();
+        if now >= target {
+            return;
+        }
+        match self.strategy {
+            Strategy::Sleep => self.timer.sleep(target - now),
+            Strategy::Spin => self.spin_until(target),
+            Strategy::Hybrid | Strategy::Deadline => {
+                if target - now > SPIN_MARGIN {
+                    self.timer.sleep(target - now - SPIN_MARGIN);
+                }
+                self.spin_until(target);
+            }
+        }
+    }
+
+    fn spin_until(&self, target: Instant) {
+        while self.timer.now() < target {
+            std::hint::spin_loop();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::{Cell, RefCell};
+    use std::rc::Rc;
+
+    const MS: Duration = Duration::from_millis(1);
+    /// What every look at the fake clock costs, so spinning gets somewhere.
+    const TICK: Duration = Duration::from_micros(1);
+
+    /// Time only moves when looked at or slept through, and sleeps overshoot by `oversleep`.
+    #[derive(Clone)]
+    struct FakeTimer {
+        now: Rc<Cell<Instant>>,
+        oversleep: Duration,
+        sleeps: Rc<RefCell<Vec<Duration>>>,
+    }
+
+    impl FakeTimer {
+        fn new(oversleep: Duration) -> FakeTimer {
+            FakeTimer {
+                now: Rc::new(Cell::new(Instant::now())),
+                oversleep,
+                sleeps: Rc::default(),
+            }
+        }
+
+        fn advance(&self, by: Duration) {
+            self.now.set(self.now.get() + by);
+        }
+
+        fn peek(&self) -> Instant {
+            self.now.get()
+        }
+
+        fn sleeps(&self) -> Vec<Duration> {
+            self.sleeps.borrow_mut().drain(..).collect()
+        }
+    }
+
+    impl Timer for FakeTimer {
+        fn now(&self) -> Instant {
+            self.advance(TICK);
+            self.now.get()
+        }
+
+        fn sleep(&self, duration: Duration) {
+            self.sleeps.borrow_mut().push(duration);
+            self.advance(duration + self.oversleep);
+        }
+    }
+
+    #[track_caller]
+    fn assert_near(actual: Duration, expected: Duration) {
+        assert!(actual.abs_diff(expected) < 50 * TICK, "{:?} isn't close to {:?}", actual, expected);
+    }
+
+    #[test]
+    fn keys_round_trip() {
+        for strategy in Strategy::ALL {
+            assert_eq!(Strategy::from_key(strategy.key()), Some(strategy));
+        }
+        assert_eq!(Strategy::from_key("nap"), None);
+    }
+
+    #[test]
+    fn uncapped_never_waits() {
+        let timer = FakeTimer::new(Duration::ZERO);
+        let mut limiter = FrameLimiter::with_timer(None, Strategy::Sleep, timer.clone());
+        assert_eq!(limiter.frame_duration(), None);
+        timer.advance(3 * MS);
+        let timing = limiter.wait();
+        assert_near(timing.frame_time, 3 * MS);
+        assert_eq!(timing.late, Duration::ZERO);
+        assert!(timer.sleeps().is_empty());
+    }
+
+    #[test]
+    fn sleep_sleeps_the_rest_and_wakes_late() {
+        let timer = FakeTimer::new(MS);
+        let mut limiter = FrameLimiter::with_timer(Some(100), Strategy::Sleep, timer.clone());
+        timer.advance(4 * MS);
+        let timing = limiter.wait();
+        let sleeps = timer.sleeps();
+        assert_eq!(sleeps.len(), 1);
+        assert_near(sleeps[0], 6 * MS);
+        assert_near(timing.frame_time, 11 * MS);
+        assert_near(timing.late, MS);
+        assert!(!timing.resynced);
+        // the oversleep carries into the next frame
+        let timing = limiter.wait();
+        assert_near(timer.sleeps()[0], 10 * MS);
+        assert_near(timing.frame_time, 11 * MS);
+    }
+
+    #[test]
+    fn spin_never_sleeps() {
+        let timer = FakeTimer::new(MS);
+        let mut limiter = FrameLimiter::with_timer(Some(100), Strategy::Spin, timer.clone());
+        let timing = limiter.wait();
+        assert!(timer.sleeps().is_empty());
+        assert_near(timing.frame_time, 10 * MS);
+        assert_near(timing.late, Duration::ZERO);
+    }
+
+    #[test]
+    fn hybrid_spins_the_margin() {
+        let timer = FakeTimer::new(Duration::ZERO);
+        let mut limiter = FrameLimiter::with_timer(Some(100), Strategy::Hybrid, timer.clone());
+        let timing = limiter.wait();
+        let sleeps = timer.sleeps();
+        assert_eq!(sleeps.len(), 1);
+        assert_near(sleeps[0], 10 * MS - SPIN_MARGIN);
+        assert_near(timing.frame_time, 10 * MS);
+        assert_near(timing.late, Duration::ZERO);
+        // too little left to be worth a sleep
+        timer.advance(9 * MS);
+        let timing = limiter.wait();
+        assert!(timer.sleeps().is_empty());
+        assert_near(timing.frame_time, 10 * MS);
+    }
+
+    #[test]
+    fn over_budget_frames_dont_wait() {
+        for strategy in Strategy::ALL {
+            let timer = FakeTimer::new(Duration::ZERO);
+            let mut limiter = FrameLimiter::with_timer(Some(100), strategy, timer.clone());
+            timer.advance(15 * MS);
+            let timing = limiter.wait();
+            assert!(timer.sleeps().is_empty(), "{:?}", strategy);
+            assert_near(timing.frame_time, 15 * MS);
+            assert_near(timing.late, 5 * MS);
+            assert!(!timing.resynced);
+        }
+    }
+
+    #[test]
+    fn deadline_doesnt_drift() {
+        let timer = FakeTimer::new(MS / 2);
+        let start = timer.peek();
+        let mut limiter = FrameLimiter::with_timer(Some(100), Strategy::Deadline, timer.clone());
+        for frame in 1..=10u32 {
+            let timing = limiter.wait();
+            // the sleep ends inside the spin margin, so oversleeping costs nothing
+            assert!(!timing.resynced);
+            assert_near(timing.late, Duration::ZERO);
+            assert_near(timer.peek() - start, frame * 10 * MS);
+        }
+    }
+
+    #[test]
+    fn deadline_takes_lateness_out_of_the_next_frame() {
+        let timer = FakeTimer::new(3 * MS);
+        let start = timer.peek();
+        let mut deadline = FrameLimiter::with_timer(Some(100), Strategy::Deadline, timer.clone());
+        for frame in 1..=10u32 {
+            let timing = deadline.wait();
+            assert_near(timing.late, MS);
+            assert_near(timer.peek() - start, frame * 10 * MS + MS);
+        }
+
+        // hybrid with the same oversleep falls another millisecond behind every frame
+        let timer = FakeTimer::new(3 * MS);
+        let start = timer.peek();
+        let mut hybrid = FrameLimiter::with_timer(Some(100), Strategy::Hybrid, timer.clone());
+        for _ in 0..10 {
+            hybrid.wait();
+        }
+        assert_near(timer.peek() - start, 110 * MS);
+    }
+
+    #[test]
+    fn deadline_resyncs_after_a_stall() {
+        let timer = FakeTimer::new(Duration::ZERO);
+        let start = timer.peek();
+        let mut limiter = FrameLimiter::with_timer(Some(100), Strategy::Deadline, timer.clone());
+        limiter.wait();
+        // less than a frame behind, caught up on the same grid
+        timer.advance(15 * MS);
+        let timing = limiter.wait();
+        assert!(!timing.resynced);
+        assert_near(timing.late, 5 * MS);
+        let timing = limiter.wait();
+        assert_near(timing.frame_time, 5 * MS);
+        assert_near(timer.peek() - start, 30 * MS);
+
+        // a long stall starts a new grid from where it ended
+        timer.advance(100 * MS);
+        let timing = limiter.wait();
+        assert!(timing.resynced);
+        assert_near(timing.late, Duration::ZERO);
+        let stalled = timer.peek();
+        let timing = limiter.wait();
+        assert!(!timing.resynced);
+        assert_near(timing.frame_time, 10 * MS);
+        assert_near(timer.peek() - stalled, 10 * MS);
+    }
+}
@@ -0,0 +1,262 @@
+use std::time::Duration;
+
+use crate::json;
+
+/// Enough for a minute at 1000 FPS.
+pub const DEFAULT_CAPACITY: usize = 60_000;
+
+/// Remembers the duration of the last `capacity` frames.
+#[derive(Debug, Clone)]
+pub struct FrameStats {
+    frames: Vec<Duration>,
+    /// Where the next frame goes once the buffer is full.
+    next: usize,
+    capacity: usize,
+}
+
+/// Numbers over a window of frames. Durations are frame times, `*_fps` are frame rates.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Summary {
+    pub frames: usize,
+    pub avg_fps: f64,
+    pub min: Duration,
+    pub max: Duration,
+    pub p50: Duration,
+    pub p95: Duration,
+    pub p99: Duration,
+    /// Average frame rate of the slowest 1% of frames.
+    pub low_1_fps: f64,
+    /// Average frame rate of the slowest 0.1% of frames.
+    pub low_01_fps: f64,
+    pub std_dev: Duration,
+}
+
+impl Summary {
+    /// Frame times in milliseconds.
+    pub fn to_json(self) -> json::Value {
+        let ms = |d: Duration| json::Value::from(d.as_secs_f64() * 1000.0);
+        json::Value::object(vec![
+            ("frames", self.frames.into()),
+            ("avg_fps", self.avg_fps.into()),
+            ("min_ms", ms(self.min)),
+            ("max_ms", ms(self.max)),
+            ("p50_ms", ms(self.p50)),
+            ("p95_ms", ms(self.p95)),
+            ("p99_ms", ms(self.p99)),
+            ("low_1_fps", self.low_1_fps.into()),
+            ("low_01_fps", self.low_01_fps.into()),
+            ("std_dev_ms", ms(self.std_dev)),
+        ])
+    }
+}
+
+impl FrameStats {
+    pub fn new(capacity: usize) -> Self {
+        FrameStats {
+            frames: Vec::with_capacity(capacity.max(1)),
+            next: 0,
+            capacity: capacity.max(1),
+        }
+    }
+
+    pub fn push(&mut self, frame_time: Duration) {
+        if self.frames.len() < self.capacity {
+            self.frames.push(frame_time);
+        } else {
+            self.frames[self.next] = frame_time;
+            self.next = (self.next + 1) % self.capacity;
+        }
+    }
+
+    /// Newest frame first.
+    pub fn recent(&self) -> impl Iterator<Item = Duration> + '_ {
+        // once full, everything before `next` was written after everything from it on
+        let (newer, older) = self.frames.split_at(self.next);
+        newer.iter().rev().chain(older.iter().rev()).copied()
+    }
+
+    /// Over the most recent frames that fit in `window`, or all of them for `None`.
+    /// `None` when there are no frames yet.
+    pub fn summary(&self, window: Option<Duration>) -> Option<Summary> {
+        let mut total = Duration::ZERO;
+        let mut frames: Vec<Duration> = Vec::new();
+        for frame in self.recent() {
+            if window.is_some_and(|w| !frames.is_empty() && total + frame > w) {
+                break;
+            }
+            total += frame;
+            frames.push(frame);
+        }
+        summarize(&frames)
+    }
+}
+
+/// `None` for no frames.
+pub fn summarize(frames: &[Duration]) -> Option<Summary> {
+    if frames.is_empty() {
+        return None;
+    }
+    let mut frames = frames.to_vec();
+    frames.sort_unstable();
+    let n = frames.len();
+    let mean = frames.iter().sum::<Duration>().as_secs_f64() / n as f64;
+    let variance = frames
+        .iter()
+        .map(|f| (f.as_secs_f64() - mean).powi(2))
+        .sum::<f64>()
+        / n as f64;
+    Some(Summary {
+        frames: n,
+        avg_fps: fps(mean),
+        min: frames[0],
+        max: frames[n - 1],
+        p50: percentile(&frames, 50.0),
+        p95: percentile(&frames, 95.0),
+        p99: percentile(&frames, 99.0),
+        low_1_fps: low(&frames, 1.0),
+        low_01_fps: low(&frames, 0.1),
+        std_dev: Duration::from_secs_f64(variance.sqrt()),
+    })
+}
+
+/// Nearest rank, `sorted` has to be ascending and not empty.
+fn percentile(sorted: &[Duration], p: f64) -> Duration {
+    let rank = (p / 100.0 * sorted.len() as f64).ceil() as usize;
+    sorted[rank.clamp(1, sorted.len()) - 1]
+}
+
+/// Frame rate over the slowest `percent` of frames, at least one frame.
+fn low(sorted: &[Duration], percent: f64) -> f64 {
+    let count = ((sorted.len() as f64 * percent / 100.0).ceil() as usize).max(1);
+    let slowest = &sorted[sorted.len() - count..];
+    let mean = slowest.iter().sum::<Duration>().as_secs_f64() / count as f64;
+    fps(mean)
+}
+
+fn fps(frame_secs: f64) -> f64 {
+    if frame_secs > 0.0 {
+        1.0 / frame_secs
+    } else {
+        0.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ms(ms: u64) -> Duration {
+        Duration::from_millis(ms)
+    }
+
+    #[track_caller]
+    fn assert_close(actual: f64, expected: f64) {
+        assert!((actual - expected).abs() < 1e-6, "{} isn't {}", actual, expected);
+    }
+
+    #[test]
+    fn recent_is_newest_first_across_the_wrap() {
+        let mut stats = FrameStats::new(3);
+        stats.push(ms(1));
+        stats.push(ms(2));
+        assert_eq!(stats.recent().collect::<Vec<_>>(), [ms(2), ms(1)]);
+        stats.push(ms(3));
+        stats.push(ms(4));
+        stats.push(ms(5));
+        assert_eq!(stats.recent().collect::<Vec<_>>(), [ms(5), ms(4), ms(3)]);
+        stats.push(ms(6));
+        assert_eq!(stats.recent().collect::<Vec<_>>(), [ms(6), ms(5), ms(4)]);
+    }
+
+    #[test]
+    fn zero_capacity_keeps_one_frame() {
+        let mut stats = FrameStats::new(0);
+        stats.push(ms(1));
+        stats.push(ms(2));
+        assert_eq!(stats.recent().collect::<Vec<_>>(), [ms(2)]);
+    }
+
+    #[test]
+    fn window_takes_the_newest_frames_that_fit() {
+        let mut stats = FrameStats::new(10);
+        for frame in [10, 20, 30, 40] {
+            stats.push(ms(frame));
+        }
+        // 40 + 30 fit, another 20 would go over
+        let summary = stats.summary(Some(ms(75))).unwrap();
+        assert_eq!(summary.frames, 2);
+        assert_eq!((summary.min, summary.max), (ms(30), ms(40)));
+        // exactly full still counts
+        assert_eq!(stats.summary(Some(ms(90))).unwrap().frames, 3);
+        assert_eq!(stats.summary(None).unwrap().frames, 4);
+    }
+
+    #[test]
+    fn window_shorter_than_a_frame_keeps_the_newest() {
+        let mut stats = FrameStats::new(10);
+        stats.push(ms(10));
+        stats.push(ms(50));
+        let summary = stats.summary(Some(ms(1))).unwrap();
+        assert_eq!(summary.frames, 1);
+        assert_eq!(summary.max, ms(50));
+    }
+
+    #[test]
+    fn empty_has_no_summary() {
+        assert_eq!(FrameStats::new(10).summary(None), None);
+        assert_eq!(summarize(&[]), None);
+    }
+
+    #[test]
+    fn single_frame() {
+        let summary = summarize(&[ms(20)]).unwrap();
+        assert_eq!(summary.frames, 1);
+        assert_close(summary.avg_fps, 50.0);
+        for d in [summary.min, summary.max, summary.p50, summary.p95, summary.p99] {
+            assert_eq!(d, ms(20));
+        }
+        assert_close(summary.low_1_fps, 50.0);
+        assert_close(summary.low_01_fps, 50.0);
+        assert_eq!(summary.std_dev, Duration::ZERO);
+    }
+
+    #[test]
+    fn percentiles_use_the_nearest_rank() {
+        // 1..=100 ms, out of order
+        let frames: Vec<Duration> = (1..=100).rev().map(ms).collect();
+        let summary = summarize(&frames).unwrap();
+        assert_eq!((summary.p50, summary.p95, summary.p99), (ms(50), ms(95), ms(99)));
+        assert_eq!((summary.min, summary.max), (ms(1), ms(100)));
+
+        // ranks round up: 0.5 * 5 = 2.5 is the 3rd, 0.95 * 5 = 4.75 the 5th
+        let summary = summarize(&[ms(5), ms(1), ms(4), ms(2), ms(3)]).unwrap();
+        assert_eq!((summary.p50, summary.p95, summary.p99), (ms(3), ms(5), ms(5)));
+    }
+
+    #[test]
+    fn lows_average_the_slowest_frames() {
+        // 1000 frames at 10 ms, except 10 at 20 ms and one of those at 100 ms
+        let mut frames = vec![ms(10); 990];
+        frames.extend(vec![ms(20); 9]);
+        frames.push(ms(100));
+        let summary = summarize(&frames).unwrap();
+        // the slowest 10: (9 * 20 + 100) / 10 = 28 ms
+        assert_close(summary.low_1_fps, 1000.0 / 28.0);
+        // the slowest 1
+        assert_close(summary.low_01_fps, 10.0);
+
+        // fewer than 100 frames still takes at least one
+        let summary = summarize(&[ms(10), ms(40)]).unwrap();
+        assert_close(summary.low_1_fps, 25.0);
+        assert_close(summary.low_01_fps, 25.0);
+    }
+
+    #[test]
+    fn std_dev_is_over_the_whole_population() {
+        // mean 5, squared deviations 9 1 1 1 0 0 4 16 sum to 32, / 8 = 4
+        let frames: Vec<Duration> = [2, 4, 4, 4, 5, 5, 7, 9].into_iter().map(ms).collect();
+        let summary = summarize(&frames).unwrap();
+        assert_close(summary.std_dev.as_secs_f64(), 0.002);
+        assert_close(summary.avg_fps, 200.0);
+    }
+}
@@ -0,0 +1,290 @@
+use std::time::{Duration, Instant};
+
+use crate::error::Error;
+use crate::json;
+use crate::session::{self, Input, ManualClock, Phase, Rules, Session, Transition};
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Action {
+    Input(Input),
+    /// Same as pressing the pause chord during a run.
+    Pause,
+    /// Same as pressing the pause chord while paused.
+    Resume,
+    /// Alt-tabbing away, minimising or locking the screen.
+    FocusLost,
+    FocusGained,
+    /// The OS's idle time says the player did something the game never got.
+    UnseenInput,
+    /// Same as hitting Escape or closing the window.
+    Quit,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct TimelineEvent {
+    pub at: Duration,
+    pub action: Action,
+}
+
+/// Parses a timeline, one event per line:
+///
+/// ```text
+/// # comments and blank lines are skipped
+/// t=0s key_down Space
+/// t=12.5s mouse_motion dx=3 dy=0
+/// t=20s mouse_wheel y=-1
+/// t=30s mouse_button_down left
+/// t=32s controller_axis value=-12000
+/// t=35s touch
+/// t=36s pause
+/// t=90s resume
+/// t=95s focus_lost
+/// t=97s focus_gained
+/// t=98s unseen_input
+/// t=100s key_down Escape
+/// ```
+pub fn parse_timeline(text: &str) -> Result<Vec<TimelineEvent>, String> {
+    let mut events: Vec<TimelineEvent> = Vec::new();
+    for (i, line) in text.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let event = parse_line(line).map_err(|e| format!("line {}: {}", i + 1, e))?;
+        if let Some(last) = events.last() {
+            if event.at < last.at {
+                return Err(format!("line {}: events must be in chronological order", i + 1));
+            }
+        }
+        events.push(event);
+    }
+    Ok(events)
+}
+
+fn parse_line(line: &str) -> Result<TimelineEvent, String> {
+    let mut parts = line.split_whitespace();
+    let at = parts
+        .next()
+        .and_then(|t| t.strip_prefix("t="))
+        .ok_or("expected the line to start with t=<seconds>s")?;
+    let secs = at
+        .strip_suffix('s')
+        .unwrap_or(at)
+        .parse::<f64>()
+        .ok()
+        .filter(|s| s.is_finite() && *s >= 0.0)
+        .ok_or_else(|| format!("invalid time `{}`", at))?;
+    let kind = parts.next().ok_or("missing event kind")?;
+    let args: Vec<&str> = parts.collect();
+    let action = match kind {
+        "key_down" => match args.as_slice() {
+            ["Escape"] => Action::Quit,
+            [_] => Action::Input(Input::KeyDown),
+            _ => return Err("key_down takes exactly one key name".to_string()),
+        },
+        "mouse_button_down" => match args.as_slice() {
+            [] | [_] => Action::Input(Input::MouseButtonDown),
+            _ => return Err("mouse_button_down takes at most one button name".to_string()),
+        },
+        "mouse_motion" => {
+            let [dx, dy] = parse_values(&args, ["dx", "dy"])?;
+            Action::Input(Input::MouseMotion { dx, dy })
+        }
+        "mouse_wheel" => {
+            let [x, y] = parse_values(&args, ["x", "y"])?;
+            Action::Input(Input::MouseWheel { x, y })
+        }
+        "controller_button_down" => match args.as_slice() {
+            [] | [_] => Action::Input(Input::ControllerButtonDown),
+            _ => return Err("controller_button_down takes at most one button name".to_string()),
+        },
+        "controller_axis" => {
+            let [value] = parse_values(&args, ["value"])?;
+            let value = i16::try_from(value).map_err(|_| "axis value out of range".to_string())?;
+            Action::Input(Input::ControllerAxis { value })
+        }
+        "touch" if args.is_empty() => Action::Input(Input::Touch),
+        "device_changed" if args.is_empty() => Action::Input(Input::DeviceChanged),
+        "pause" if args.is_empty() => Action::Pause,
+        "resume" if args.is_empty() => Action::Resume,
+        "focus_lost" if args.is_empty() => Action::FocusLost,
+        "focus_gained" if args.is_empty() => Action::FocusGained,
+        "unseen_input" if args.is_empty() => Action::UnseenInput,
+        "quit" if args.is_empty() => Action::Quit,
+        _ => return Err(format!("unknown event `{}`", kind)),
+    };
+    // the simulation counts from an `Instant`, which only goes so far
+    let at = Duration::try_from_secs_f64(secs)
+        .ok()
+        .filter(|at| Instant::now().checked_add(*at).is_some())
+        .ok_or_else(|| format!("time `{}` is too far out", at))?;
+    Ok(TimelineEvent { at, action })
+}
+
+/// Missing values default to 0.
+fn parse_values<const N: usize>(args: &[&str], names: [&str; N]) -> Result<[i32; N], String> {
+    let mut values = [0; N];
+    for arg in args {
+        let (key, value) = arg
+            .split_once('=')
+            .ok_or_else(|| format!("expected key=value, got `{}`", arg))?;
+        let i = names
+            .iter()
+            .position(|n| *n == key)
+            .ok_or_else(|| format!("unknown argument `{}`", key))?;
+        values[i] = value
+            .parse::<i32>()
+            .map_err(|_| format!("invalid value for {}: `{}`", key, value))?;
+    }
+    Ok(values)
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Outcome {
+    pub started_at: Duration,
+    pub inactive: Duration,
+    pub paused: Duration,
+    pub reasons: session::Reasons,
+    /// `false` when the timeline ran out while the player was still inactive.
+    pub failed: bool,
+    /// The window lost focus and the run kept going.
+    pub background: bool,
+    /// The OS said the player was active while nothing came in.
+    pub idle_mismatch: bool,
+}
+
+impl Outcome {
+    pub fn to_json(&self) -> json::Value {
+        let reasons: Vec<&str> = self.reasons.iter().map(|r| r.key()).collect();
+        json::Value::object(vec![
+            ("started_at", self.started_at.as_secs_f64().into()),
+            ("inactive_for", self.inactive.as_secs_f64().into()),
+            ("paused_for", self.paused.as_secs_f64().into()),
+            ("reasons", reasons.into()),
+            ("failed", self.failed.into()),
+            ("background", self.background.into()),
+            ("idle_mismatch", self.idle_mismatch.into()),
+        ])
+    }
+}
+
+/// Feeds the timeline through a [`Session`] the same way the event loop does.
+pub fn simulate(events: &[TimelineEvent], rules: Rules) -> Vec<Outcome> {
+    let origin = Instant::now();
+    let now = ManualClock::new(origin);
+    let mut session = Session::with_clock(now.clone()).with_rules(rules);
+    let mut started_at = Duration::ZERO;
+    let mut outcomes = Vec::new();
+
+    for event in events {
+        let Some(at) = origin.checked_add(event.at) else {
+            break;
+        };
+        now.set(at);
+        while let Some(transition) = session.tick() {
+            match transition {
+                // the countdown ran out somewhere between the last event and this one
+                Transition::Activated => started_at = event.at.saturating_sub(session.elapsed()),
+                Transition::Dismissed => outcomes.push(outcome(&session, started_at)),
+                _ => {}
+            }
+        }
+        match event.action {
+            Action::Quit => break,
+            Action::Pause => {
+                session.pause();
+            }
+            Action::Resume => {
+                session.resume();
+            }
+            Action::FocusLost => {
+                session.focus_lost();
+            }
+            Action::FocusGained => {
+                session.focus_gained();
+            }
+            Action::UnseenInput => {
+                session.unseen_input(at);
+            }
+            Action::Input(input) => match session.handle(input) {
+                Some(Transition::Activated) => started_at = event.at,
+                Some(Transition::Dismissed) => outcomes.push(outcome(&session, started_at)),
+                _ => {}
+            },
+        }
+    }
+    // still counting down, the run never started
+    if matches!(
+        session.phase(),
+        Phase::Armed | Phase::Tracking | Phase::Paused | Phase::Resuming | Phase::Failed
+    ) {
+        outcomes.push(outcome(&session, started_at));
+    }
+    outcomes
+}
+
+fn outcome(session: &Session<ManualClock>, started_at: Duration) -> Outcome {
+    Outcome {
+        started_at,
+        inactive: session.elapsed(),
+        paused: session.paused(),
+        reasons: session.reasons(),
+        failed: matches!(session.phase(), Phase::Failed | Phase::Cooldown | Phase::Intro),
+        background: session.background(),
+        idle_mismatch: session.idle_mismatch(),
+    }
+}
+
+pub fn run(path: &std::path::Path, rules: Rules) -> Result<(), Error> {
+    let text = std::fs::read_to_string(path)
+        .map_err(|e| Error::Io(format!("Failed to read {}", path.display()), e))?;
+    let events = parse_timeline(&text).map_err(Error::Timeline)?;
+    let outcomes: Vec<json::Value> = simulate(&events, rules).iter().map(Outcome::to_json).collect();
+    println!("{}", json::Value::object(vec![("runs", outcomes.into())]));
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn runs(timeline: &str) -> Vec<Outcome> {
+        simulate(&parse_timeline(timeline).unwrap(), Rules::default())
+    }
+
+    #[test]
+    fn failed_run() {
+        let outcomes = runs("t=0s key_down Space\nt=3.5s key_down A\nt=20s mouse_button_down\nt=60s quit");
+        assert_eq!(outcomes.len(), 1);
+        assert_eq!(outcomes[0].inactive, Duration::from_secs(17));
+        assert!(outcomes[0].failed);
+    }
+
+    #[test]
+    fn ends_while_tracking() {
+        let outcomes = runs("t=0s key_down Space\nt=3.5s key_down A\nt=10s quit");
+        assert_eq!(outcomes.len(), 1);
+        assert_eq!(outcomes[0].inactive, Duration::from_secs(7));
+        assert!(!outcomes[0].failed);
+    }
+
+    #[test]
+    fn ends_before_the_first_input() {
+        // still counting down
+        assert!(runs("t=0s key_down Space\nt=2s quit").is_empty());
+        // the first input never came to be swallowed, the player sat through all of it
+        let outcomes = runs("t=0s key_down Space\nt=600s quit");
+        assert_eq!(outcomes.len(), 1);
+        assert_eq!(outcomes[0].started_at, Duration::from_secs(3));
+        assert_eq!(outcomes[0].inactive, Duration::from_secs(597));
+        assert!(!outcomes[0].failed);
+    }
+
+    #[test]
+    fn times_too_far_out_are_refused() {
+        for line in ["t=1e30s quit", "t=1e19s quit"] {
+            let err = parse_timeline(line).unwrap_err();
+            assert!(err.contains("too far out"), "{}", err);
+        }
+    }
+}
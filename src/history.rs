@@ -0,0 +1,267 @@
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use crate::session::{Reason, Reasons};
+
+const HEADER: &str = "nothing-history";
+const VERSION: u32 = 1;
+
+#[derive(Debug, Clone)]
+pub struct Record {
+    pub started: SystemTime,
+    pub inactive: Duration,
+    pub reasons: Reasons,
+    /// `None` when the run was played uncapped.
+    pub fps_cap: Option<u64>,
+    /// The game was shut down while the run was still going.
+    pub interrupted: bool,
+    /// The window lost focus and the run kept going.
+    pub background: bool,
+    /// The OS said the player was active during the run while the game got nothing.
+    pub idle_mismatch: bool,
+    /// Time spent paused, not part of `inactive`.
+    pub paused: Duration,
+}
+
+impl Record {
+    /// Played to the end with focus and nothing the OS saw that the game didn't, so it can be a best.
+    pub fn clean(&self) -> bool {
+        !self.interrupted && !self.background && !self.idle_mismatch
+    }
+
+    // started_unix_ms \t inactive_ms \t reason,reason \t fps_cap \t flag,flag \t paused_ms
+    fn to_line(&self) -> String {
+        let started = self
+            .started
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_millis();
+        let reasons: Vec<&str> = self.reasons.iter().map(|r| r.key()).collect();
+        let cap = match self.fps_cap {
+            Some(c) => c.to_string(),
+            None => "unlimited".to_string(),
+        };
+        let mut flags = Vec::new();
+        if self.interrupted {
+            flags.push("interrupted");
+        }
+        if self.background {
+            flags.push("background");
+        }
+        if self.idle_mismatch {
+            flags.push("idle_mismatch");
+        }
+        format!(
+            "{}\t{}\t{}\t{}\t{}\t{}",
+            started,
+            self.inactive.as_millis(),
+            reasons.join(","),
+            cap,
+            flags.join(","),
+            self.paused.as_millis()
+        )
+    }
+
+    fn from_line(line: &str) -> Option<Record> {
+        let mut fields = line.split('\t');
+        let started = fields.next()?.parse::<u64>().ok()?;
+        let inactive = fields.next()?.parse::<u64>().ok()?;
+        let mut reasons = Reasons::default();
+        for key in fields.next()?.split(',').filter(|k| !k.is_empty()) {
+            reasons.insert(Reason::from_key(key)?);
+        }
+        let fps_cap = match fields.next()? {
+            "unlimited" => None,
+            c => Some(c.parse::<u64>().ok()?),
+        };
+        let mut interrupted = false;
+        let mut background = false;
+        let mut idle_mismatch = false;
+        for flag in fields.next()?.split(',').filter(|f| !f.is_empty()) {
+            match flag {
+                "interrupted" => interrupted = true,
+                "background" => background = true,
+                "idle_mismatch" => idle_mismatch = true,
+                // from a newer version, the run itself is still good
+                _ => {}
+            }
+        }
+        let paused = fields.next()?.parse::<u64>().ok()?;
+        Some(Record {
+            started: UNIX_EPOCH + Duration::from_millis(started),
+            inactive: Duration::from_millis(inactive),
+            reasons,
+            fps_cap,
+            interrupted,
+            background,
+            idle_mismatch,
+            paused: Duration::from_millis(paused),
+        })
+    }
+}
+
+pub struct History {
+    path: PathBuf,
+    records: Vec<Record>,
+}
+
+impl History {
+    pub fn default_path() -> Option<PathBuf> {
+        crate::paths::data_dir().map(|d| d.join("history.tsv"))
+    }
+
+    /// Loads the history at `path`, a missing file is just an empty history.
+    pub fn load(path: &Path) -> std::io::Result<History> {
+        let mut records = Vec::new();
+        match std::fs::read_to_string(path) {
+            Ok(contents) => {
+                let mut lines = contents.lines();
+                if let Some(header) = lines.next() {
+                    check_header(header)?;
+                }
+                for (i, line) in lines.enumerate() {
+                    if line.trim().is_empty() {
+                        continue;
+                    }
+                    match Record::from_line(line) {
+                        Some(r) => records.push(r),
+                        None => {
+                            return Err(std::io::Error::new(
+                                std::io::ErrorKind::InvalidData,
+                                format!("{}: malformed record on line {}", path.display(), i + 2),
+                            ))
+                        }
+                    }
+                }
+            }
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+            Err(e) => return Err(e),
+        }
+        Ok(History {
+            path: path.to_path_buf(),
+            records,
+        })
+    }
+
+    /// The longest clean run, flagged ones stay in the file but don't count.
+    pub fn best(&self) -> Option<&Record> {
+        self.records.iter().filter(|r| r.clean()).max_by_key(|r| r.inactive)
+    }
+
+    /// Appends the record to the file on disk and keeps it in memory.
+    pub fn push(&mut self, record: Record) -> std::io::Result<()> {
+        if let Some(parent) = self.path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let fresh = !self.path.exists();
+        let mut file = std::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)?;
+        if fresh {
+            writeln!(file, "{} v{}", HEADER, VERSION)?;
+        }
+        writeln!(file, "{}", record.to_line())?;
+        self.records.push(record);
+        Ok(())
+    }
+}
+
+fn check_header(header: &str) -> std::io::Result<()> {
+    let version = header
+        .strip_prefix(HEADER)
+        .and_then(|v| v.trim().strip_prefix('v'))
+        .and_then(|v| v.parse::<u32>().ok());
+    match version {
+        Some(VERSION) => Ok(()),
+        Some(v) => Err(std::io::Error::new(
+            std::io::ErrorKind::InvalidData,
+            format!("unsupported history version {} (expected {})", v, VERSION),
+        )),
+        None => Err(std::io::Error::new(
+            std::io::ErrorKind::InvalidData,
+            "not a history file",
+        )),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn record(inactive_secs: u64) -> Record {
+        Record {
+            started: UNIX_EPOCH + Duration::from_secs(1_700_000_000),
+            inactive: Duration::from_secs(inactive_secs),
+            reasons: Reasons::default(),
+            fps_cap: Some(60),
+            interrupted: false,
+            background: false,
+            idle_mismatch: false,
+            paused: Duration::ZERO,
+        }
+    }
+
+    fn history(records: Vec<Record>) -> History {
+        History {
+            path: PathBuf::new(),
+            records,
+        }
+    }
+
+    #[test]
+    fn lines_round_trip() {
+        let original = Record {
+            reasons: Reasons::from_bits(0b101),
+            fps_cap: None,
+            interrupted: true,
+            idle_mismatch: true,
+            paused: Duration::from_millis(1500),
+            ..record(42)
+        };
+        let line = original.to_line();
+        assert_eq!(line, "1700000000000\t42000\tkeyboard,mouse_motion\tunlimited\tinterrupted,idle_mismatch\t1500");
+        let read = Record::from_line(&line).unwrap();
+        assert_eq!(read.to_line(), line);
+    }
+
+    #[test]
+    fn short_lines_are_malformed() {
+        assert!(Record::from_line("1700000000000\t42000\tkeyboard\t60").is_none());
+        assert!(Record::from_line("1700000000000\t42000\tkeyboard\t60\t").is_none());
+    }
+
+    #[test]
+    fn unknown_flags_are_skipped() {
+        let read = Record::from_line("1700000000000\t42000\t\t60\tbackground,from_the_future\t0").unwrap();
+        assert!(read.background);
+        assert!(!read.interrupted);
+    }
+
+    #[test]
+    fn headers() {
+        assert!(check_header(&format!("nothing-history v{}", VERSION)).is_ok());
+        assert!(check_header(&format!("nothing-history v{}", VERSION + 1)).is_err());
+        assert!(check_header("something else").is_err());
+    }
+
+    #[test]
+    fn best_skips_flagged_runs() {
+        let history = history(vec![
+            record(10),
+            Record { interrupted: true, ..record(50) },
+            Record { background: true, ..record(40) },
+            Record { idle_mismatch: true, ..record(30) },
+            record(20),
+        ]);
+        assert_eq!(history.best().map(|r| r.inactive), Some(Duration::from_secs(20)));
+        assert_eq!(history.records.len(), 5);
+    }
+
+    #[test]
+    fn no_clean_runs_no_best() {
+        let history = history(vec![Record { background: true, ..record(10) }]);
+        assert!(history.best().is_none());
+    }
+}
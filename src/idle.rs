@@ -0,0 +1,428 @@
+use std::sync::mpsc::{self, Receiver};
+use std::time::{Duration, Instant};
+
+use crate::log;
+
+/// How often the OS gets asked.
+const INTERVAL: Duration = Duration::from_secs(1);
+/// Input reaches the event loop a little after the OS counts it.
+const SLACK: Duration = Duration::from_millis(250);
+/// SDL keeps the screensaver off by resetting the X server's idle time this often, poking GNOME's over D-Bus at the
+/// same time. Each one comes on the first frame after the last one's 30 seconds are up.
+const SDL_RESETS: Duration = Duration::from_secs(30);
+/// How long `gdbus` gets to answer.
+const CALL_TIMEOUT: Duration = Duration::from_millis(500);
+
+/// Where the OS's idle time comes from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Source {
+    /// Whichever of the others works here, D-Bus before X11 on Wayland.
+    Auto,
+    /// The ScreenSaver extension.
+    X11,
+    /// GNOME's `org.gnome.Mutter.IdleMonitor`, through `gdbus`.
+    DBus,
+    /// `GetLastInputInfo`.
+    Windows,
+    Off,
+}
+
+impl Source {
+    pub const ALL: [Source; 5] = [Source::Auto, Source::X11, Source::DBus, Source::Windows, Source::Off];
+
+    pub fn key(&self) -> &'static str {
+        match self {
+            Source::Auto => "auto",
+            Source::X11 => "x11",
+            Source::DBus => "dbus",
+            Source::Windows => "windows",
+            Source::Off => "off",
+        }
+    }
+
+    pub fn from_key(key: &str) -> Option<Source> {
+        Source::ALL.into_iter().find(|s| s.key() == key)
+    }
+}
+
+/// Asks the OS how long the user has been idle, in the background since some ways of asking are slow.
+pub struct IdleMonitor {
+    source: Source,
+    /// When the OS last saw input, as of each answer.
+    samples: Receiver<Instant>,
+    /// The last time SDL reset the idle time, for sources that see it.
+    sdl_reset: Option<Instant>,
+}
+
+impl IdleMonitor {
+    /// `None` when `source` is off or can't be asked here.
+    pub fn start(source: Source) -> Option<IdleMonitor> {
+        if source == Source::Off {
+            return None;
+        }
+        let (ready_tx, ready_rx) = mpsc::channel();
+        let (samples_tx, samples) = mpsc::channel();
+        std::thread::spawn(move || {
+            let mut backend = match Backend::open(source) {
+                Some(b) => b,
+                None => {
+                    let _ = ready_tx.send(None);
+                    return;
+                }
+            };
+            let _ = ready_tx.send(Some(backend.source()));
+            loop {
+                let asked = Instant::now();
+                if let Some(idle) = backend.idle() {
+                    // the receiver went away with the monitor
+                    if samples_tx.send(asked.checked_sub(idle).unwrap_or(asked)).is_err() {
+                        return;
+                    }
+                }
+                std::thread::sleep(INTERVAL);
+            }
+        });
+        let source = ready_rx.recv().ok().flatten()?;
+        Some(IdleMonitor {
+            source,
+            samples,
+            sdl_reset: None,
+        })
+    }
+
+    pub fn source(&self) -> Source {
+        self.source
+    }
+
+    /// SDL resets the idle time the first time it looks for events, at `at`, and every 30 seconds after.
+    /// Those resets look like input the game never got, so input the OS saw when one was due doesn't count.
+    pub fn sdl_resets_from(&mut self, at: Instant) {
+        if matches!(self.source, Source::X11 | Source::DBus) {
+            self.sdl_reset = Some(at);
+        }
+    }
+
+    /// When the OS saw input the event loop didn't, going by the latest answer. `seen` is the
+    /// last time the event loop got any input and `polled` when it last looked for some.
+    pub fn unseen(&mut self, seen: Instant, polled: Instant) -> Option<Instant> {
+        let last = self.samples.try_iter().last()?;
+        // anything later may just not have arrived yet
+        if last <= seen + SLACK || last + SLACK >= polled {
+            return None;
+        }
+        match self.sdl_reset {
+            Some(reset) if sdl_reset_due(reset, last) => {
+                self.sdl_reset = Some(last);
+                None
+            }
+            _ => Some(last),
+        }
+    }
+}
+
+/// Whether SDL could have reset the idle time at `at`, the last reset having been at `reset`. Every
+/// reset can come up to a frame late, which `SLACK` covers for each one since.
+fn sdl_reset_due(reset: Instant, at: Instant) -> bool {
+    let Some(since) = at.checked_duration_since(reset) else {
+        return false;
+    };
+    let resets = ((since + SDL_RESETS / 2).as_secs_f64() / SDL_RESETS.as_secs_f64()) as u32;
+    let due = SDL_RESETS * resets;
+    since + SLACK >= due && since <= due + SLACK * (resets + 1)
+}
+
+enum Backend {
+    #[cfg(target_os = "linux")]
+    X11(x11::ScreenSaver),
+    DBus,
+    Windows,
+}
+
+impl Backend {
+    fn open(source: Source) -> Option<Backend> {
+        match source {
+            Source::Off => None,
+            #[cfg(target_os = "linux")]
+            Source::X11 => x11::ScreenSaver::open().map(Backend::X11),
+            #[cfg(not(target_os = "linux"))]
+            Source::X11 => None,
+            Source::DBus => mutter_idle().map(|_| Backend::DBus),
+            Source::Windows => windows_idle().map(|_| Backend::Windows),
+            Source::Auto => {
+                // XWayland only sees input that goes to X clients
+                let wayland = std::env::var("XDG_SESSION_TYPE").is_ok_and(|t| t == "wayland");
+                let order: &[Source] = if wayland {
+                    &[Source::Windows, Source::DBus]
+                } else {
+                    &[Source::Windows, Source::X11, Source::DBus]
+                };
+                order.iter().find_map(|s| Backend::open(*s))
+            }
+        }
+    }
+
+    fn source(&self) -> Source {
+        match self {
+            #[cfg(target_os = "linux")]
+            Backend::X11(_) => Source::X11,
+            Backend::DBus => Source::DBus,
+            Backend::Windows => Source::Windows,
+        }
+    }
+
+    fn idle(&mut self) -> Option<Duration> {
+        match self {
+            #[cfg(target_os = "linux")]
+            Backend::X11(s) => s.idle(),
+            Backend::DBus => mutter_idle(),
+            Backend::Windows => windows_idle(),
+        }
+    }
+}
+
+/// `GetIdletime` on GNOME's idle monitor, given up on after `CALL_TIMEOUT`.
+fn mutter_idle() -> Option<Duration> {
+    let child = std::process::Command::new("gdbus")
+        .args([
+            "call",
+            "--session",
+            "--dest",
+            "org.gnome.Mutter.IdleMonitor",
+            "--object-path",
+            "/org/gnome/Mutter/IdleMonitor/Core",
+            "--method",
+            "org.gnome.Mutter.IdleMonitor.GetIdletime",
+        ])
+        .stdout(std::process::Stdio::piped())
+        .stderr(std::process::Stdio::null())
+        .spawn();
+    let mut child = match child {
+        Ok(c) => c,
+        Err(e) => {
+            log::debug!("idle", "can't run gdbus: {}", e);
+            return None;
+        }
+    };
+    let deadline = Instant::now() + CALL_TIMEOUT;
+    loop {
+        match child.try_wait() {
+            Ok(Some(status)) if status.success() => break,
+            Ok(None) if Instant::now() < deadline => std::thread::sleep(Duration::from_millis(10)),
+            Ok(None) => {
+                log::debug!("idle", "gdbus took longer than {:?}", CALL_TIMEOUT);
+                let _ = child.kill();
+                let _ = child.wait();
+                return None;
+            }
+            _ => return None,
+        }
+    }
+    // the reply is far smaller than the pipe's buffer, so it's all there once gdbus exits
+    let mut reply = String::new();
+    std::io::Read::read_to_string(&mut child.stdout.take()?, &mut reply).ok()?;
+    parse_idletime(&reply)
+}
+
+/// `GetIdletime`'s reply, `(uint64 1234,)` in milliseconds.
+fn parse_idletime(reply: &str) -> Option<Duration> {
+    let ms = reply.trim().strip_prefix("(uint64 ")?.strip_suffix(",)")?;
+    ms.parse().ok().map(Duration::from_millis)
+}
+
+#[cfg(target_os = "windows")]
+fn windows_idle() -> Option<Duration> {
+    use winapi::um::sysinfoapi::GetTickCount;
+    use winapi::um::winuser::{GetLastInputInfo, LASTINPUTINFO};
+    let mut info = LASTINPUTINFO {
+        cbSize: std::mem::size_of::<LASTINPUTINFO>() as u32,
+        dwTime: 0,
+    };
+    if unsafe { GetLastInputInfo(&mut info) } == 0 {
+        return None;
+    }
+    // both wrap around after 49.7 days
+    let idle = unsafe { GetTickCount() }.wrapping_sub(info.dwTime);
+    Some(Duration::from_millis(idle as u64))
+}
+
+#[cfg(not(target_os = "windows"))]
+fn windows_idle() -> Option<Duration> {
+    None
+}
+
+/// libX11 and libXss are loaded when asked for, so the game still starts without them.
+#[cfg(target_os = "linux")]
+mod x11 {
+    use std::os::raw::{c_char, c_int, c_ulong, c_void};
+    use std::time::Duration;
+
+    #[repr(C)]
+    struct Info {
+        window: c_ulong,
+        state: c_int,
+        kind: c_int,
+        til_or_since: c_ulong,
+        idle: c_ulong,
+        event_mask: c_ulong,
+    }
+
+    type QueryInfo = unsafe extern "C" fn(*mut c_void, c_ulong, *mut Info) -> c_int;
+
+    /// A display connection of its own, only ever used from the thread that opened it.
+    pub struct ScreenSaver {
+        display: *mut c_void,
+        root: c_ulong,
+        info: *mut Info,
+        query_info: QueryInfo,
+    }
+
+    impl ScreenSaver {
+        pub fn open() -> Option<ScreenSaver> {
+            unsafe {
+                let x11 = libc::dlopen(c"libX11.so.6".as_ptr(), libc::RTLD_NOW);
+                let xss = libc::dlopen(c"libXss.so.1".as_ptr(), libc::RTLD_NOW);
+                if x11.is_null() || xss.is_null() {
+                    return None;
+                }
+                // SAFETY: `Display *XOpenDisplay(char *display_name)` in Xlib.h
+                let open_display: unsafe extern "C" fn(*const c_char) -> *mut c_void =
+                    std::mem::transmute(symbol(x11, c"XOpenDisplay")?);
+                // SAFETY: `Window XDefaultRootWindow(Display *display)` in Xlib.h, Window being an XID
+                let root_window: unsafe extern "C" fn(*mut c_void) -> c_ulong =
+                    std::mem::transmute(symbol(x11, c"XDefaultRootWindow")?);
+                // SAFETY: `Bool XScreenSaverQueryExtension(Display *dpy, int *event_base, int *error_base)`
+                // in scrnsaver.h, Bool being an int
+                let query_extension: unsafe extern "C" fn(*mut c_void, *mut c_int, *mut c_int) -> c_int =
+                    std::mem::transmute(symbol(xss, c"XScreenSaverQueryExtension")?);
+                // SAFETY: `XScreenSaverInfo *XScreenSaverAllocInfo(void)` in scrnsaver.h, `Info` has its layout
+                let alloc_info: unsafe extern "C" fn() -> *mut Info =
+                    std::mem::transmute(symbol(xss, c"XScreenSaverAllocInfo")?);
+                // SAFETY: `Status XScreenSaverQueryInfo(Display *dpy, Drawable drawable, XScreenSaverInfo *info)`
+                // in scrnsaver.h, Status being an int and Drawable an XID
+                let query_info: QueryInfo = std::mem::transmute(symbol(xss, c"XScreenSaverQueryInfo")?);
+
+                let display = open_display(std::ptr::null());
+                if display.is_null() {
+                    return None;
+                }
+                let (mut event_base, mut error_base) = (0, 0);
+                if query_extension(display, &mut event_base, &mut error_base) == 0 {
+                    return None;
+                }
+                let info = alloc_info();
+                if info.is_null() {
+                    return None;
+                }
+                Some(ScreenSaver {
+                    display,
+                    root: root_window(display),
+                    info,
+                    query_info,
+                })
+            }
+        }
+
+        pub fn idle(&mut self) -> Option<Duration> {
+            unsafe {
+                if (self.query_info)(self.display, self.root, self.info) == 0 {
+                    return None;
+                }
+                let idle: c_ulong = (*self.info).idle;
+                Some(Duration::from_millis(idle as u64))
+            }
+        }
+    }
+
+    unsafe fn symbol(library: *mut c_void, name: &std::ffi::CStr) -> Option<*mut c_void> {
+        let symbol = libc::dlsym(library, name.as_ptr());
+        (!symbol.is_null()).then_some(symbol)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn monitor(source: Source) -> (IdleMonitor, mpsc::Sender<Instant>) {
+        let (tx, samples) = mpsc::channel();
+        let monitor = IdleMonitor {
+            source,
+            samples,
+            sdl_reset: None,
+        };
+        (monitor, tx)
+    }
+
+    #[test]
+    fn quiet_run_over_30_seconds_isnt_flagged() {
+        let (mut monitor, tx) = monitor(Source::X11);
+        let start = Instant::now();
+        monitor.sdl_resets_from(start);
+        // nothing touched since the run started, only SDL resetting the idle time a frame or so late each time
+        let mut reset = start;
+        for s in 1..=200 {
+            if s % 30 == 0 {
+                reset += SDL_RESETS + Duration::from_millis(17);
+            }
+            tx.send(reset).unwrap();
+            assert_eq!(monitor.unseen(start, start + Duration::from_secs(s)), None, "after {}s", s);
+        }
+    }
+
+    #[test]
+    fn input_between_sdl_resets_is_flagged() {
+        let (mut monitor, tx) = monitor(Source::DBus);
+        let start = Instant::now();
+        monitor.sdl_resets_from(start);
+        let touched = start + Duration::from_secs(45);
+        tx.send(touched).unwrap();
+        assert_eq!(monitor.unseen(start, start + Duration::from_secs(46)), Some(touched));
+        // SDL's next reset still comes on time
+        let reset = start + SDL_RESETS * 2;
+        tx.send(reset).unwrap();
+        assert_eq!(monitor.unseen(start, reset + Duration::from_secs(1)), None);
+    }
+
+    #[test]
+    fn windows_idle_time_isnt_reset_by_sdl() {
+        let (mut monitor, tx) = monitor(Source::Windows);
+        let start = Instant::now();
+        monitor.sdl_resets_from(start);
+        let touched = start + SDL_RESETS;
+        tx.send(touched).unwrap();
+        assert_eq!(monitor.unseen(start, touched + Duration::from_secs(1)), Some(touched));
+    }
+
+    #[test]
+    fn recorded_gdbus_replies() {
+        assert_eq!(parse_idletime("(uint64 48213,)\n"), Some(Duration::from_millis(48213)));
+        assert_eq!(parse_idletime("(uint64 0,)\n"), Some(Duration::ZERO));
+        // what comes back on stdout when Mutter isn't there is nothing at all, the error goes to stderr
+        assert_eq!(parse_idletime(""), None);
+        assert_eq!(parse_idletime("(uint32 12,)\n"), None);
+        assert_eq!(parse_idletime("(uint64 -1,)\n"), None);
+    }
+
+    #[test]
+    fn input_the_loop_missed_is_flagged() {
+        let (mut monitor, tx) = monitor(Source::DBus);
+        let start = Instant::now();
+        let touched = start + Duration::from_secs(5);
+        tx.send(start).unwrap();
+        tx.send(touched).unwrap();
+        assert_eq!(monitor.unseen(start, start + Duration::from_secs(6)), Some(touched));
+        // too close to the last poll, the event may still be on its way
+        tx.send(touched).unwrap();
+        assert_eq!(monitor.unseen(start, touched + Duration::from_millis(100)), None);
+        // the event loop got it
+        tx.send(touched).unwrap();
+        assert_eq!(monitor.unseen(touched, start + Duration::from_secs(6)), None);
+    }
+
+    #[test]
+    fn no_answer_no_verdict() {
+        let (mut monitor, _tx) = monitor(Source::Windows);
+        let start = Instant::now();
+        assert_eq!(monitor.unseen(start, start + Duration::from_secs(60)), None);
+    }
+}
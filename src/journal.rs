@@ -0,0 +1,385 @@
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use crate::forensics::Forensics;
+use crate::sensitivity::Sensitivity;
+use crate::session::{Clock, CountdownInput, FocusPolicy, Input, ManualClock, Reasons, Rules, Session};
+
+const MAGIC: &[u8; 4] = b"NTHJ";
+const VERSION: u8 = 1;
+
+const TICK: u8 = 0x80;
+const OFFENDER: u8 = 0x81;
+const PAUSE: u8 = 0x82;
+const RESUME: u8 = 0x83;
+const FOCUS_LOST: u8 = 0x84;
+const FOCUS_GAINED: u8 = 0x85;
+const UNSEEN_INPUT: u8 = 0x86;
+const END: u8 = 0xff;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Entry {
+    Input(Input),
+    /// A `tick` that changed the phase. Ticks that did nothing aren't kept.
+    Tick,
+    /// Follows the input that failed the run, with what the front end knew about it.
+    Offender(String),
+    Pause,
+    Resume,
+    FocusLost,
+    FocusGained,
+    /// The OS's idle time said there was input the game never got.
+    UnseenInput,
+    /// The run was dismissed or the game quit.
+    End,
+}
+
+/// Everything needed to play a run back through a [`Session`] and land on the same result.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Journal {
+    pub started: SystemTime,
+    pub rules: Rules,
+    /// Offsets from the moment the run was activated.
+    pub entries: Vec<(Duration, Entry)>,
+}
+
+/// Builds up a [`Journal`] while a run is going.
+pub struct Recorder {
+    journal: Journal,
+    origin: Instant,
+}
+
+impl Recorder {
+    /// Call right after `input` at `at` started the countdown, or the run when there's none.
+    pub fn start<C: Clock>(session: &Session<C>, input: Input, at: Instant) -> Recorder {
+        let mut recorder = Recorder {
+            journal: Journal {
+                started: SystemTime::now(),
+                rules: session.rules(),
+                entries: Vec::new(),
+            },
+            origin: at,
+        };
+        if !session.focused() {
+            recorder.record(at, Entry::FocusLost);
+        }
+        recorder.record(at, Entry::Input(input));
+        recorder
+    }
+
+    pub fn record(&mut self, at: Instant, entry: Entry) {
+        self.journal.entries.push((at.saturating_duration_since(self.origin), entry));
+    }
+
+    pub fn finish(mut self, at: Instant) -> Journal {
+        self.record(at, Entry::End);
+        self.journal
+    }
+}
+
+impl Journal {
+    pub fn default_dir() -> Option<PathBuf> {
+        crate::paths::data_dir().map(|d| d.join("journal"))
+    }
+
+    /// Writes to `<dir>/<start in unix ms>.nthj` and returns that path.
+    pub fn save(&self, dir: &Path) -> std::io::Result<PathBuf> {
+        std::fs::create_dir_all(dir)?;
+        let path = dir.join(format!("{}.nthj", unix_ms(self.started)));
+        std::fs::write(&path, self.encode())?;
+        Ok(path)
+    }
+
+    pub fn load(path: &Path) -> std::io::Result<Journal> {
+        let bytes = std::fs::read(path)?;
+        Journal::decode(&bytes).map_err(|e| {
+            std::io::Error::new(std::io::ErrorKind::InvalidData, format!("{}: {}", path.display(), e))
+        })
+    }
+
+    /// Plays the entries back on a fresh session with the recorded rules and returns it,
+    /// and what the result screen knew, as they were when the journal ended.
+    pub fn replay(&self) -> (Session<ManualClock>, Forensics) {
+        let origin = Instant::now();
+        let clock = ManualClock::new(origin);
+        let mut session = Session::with_clock(clock.clone()).with_rules(self.rules);
+        let mut forensics = Forensics::default();
+        for (at, entry) in &self.entries {
+            clock.set(origin + *at);
+            match entry {
+                Entry::Input(input) => {
+                    let transition = session.handle(*input);
+                    forensics.observe(*input, transition, session.elapsed(), None);
+                }
+                Entry::Tick => {
+                    let transition = session.tick();
+                    forensics.observe_tick(transition, session.elapsed());
+                }
+                Entry::Offender(detail) => forensics.set_detail(detail.clone()),
+                Entry::Pause => {
+                    session.pause();
+                }
+                Entry::Resume => {
+                    session.resume();
+                }
+                Entry::FocusLost => {
+                    let transition = session.focus_lost();
+                    forensics.observe_focus_lost(transition, session.elapsed(), "window lost focus".to_string());
+                }
+                Entry::FocusGained => {
+                    session.focus_gained();
+                }
+                Entry::UnseenInput => {
+                    session.unseen_input(origin + *at);
+                }
+                Entry::End => break,
+            }
+        }
+        (session, forensics)
+    }
+
+    /// Header, then one entry per record: time since the previous one in nanoseconds,
+    /// a kind byte and the kind's values. Numbers are LEB128, signed ones zigzagged.
+    pub fn encode(&self) -> Vec<u8> {
+        let mut out = Vec::new();
+        out.extend_from_slice(MAGIC);
+        out.push(VERSION);
+        out.push(
+            self.rules.ignore_first_input as u8
+                | (self.rules.wait_for_key as u8) << 1
+                | ((self.rules.countdown_input == CountdownInput::Restart) as u8) << 2
+                | (FocusPolicy::ALL.iter().position(|p| *p == self.rules.focus).unwrap() as u8) << 3,
+        );
+        put(&mut out, unix_ms(self.started));
+        let sensitivity = &self.rules.sensitivity;
+        put(&mut out, sensitivity.motion_dead_zone as u64);
+        put(&mut out, sensitivity.min_wheel_delta as u64);
+        put(&mut out, sensitivity.axis_threshold as u64);
+        put(&mut out, sensitivity.ignored.bits() as u64);
+        put(&mut out, self.rules.countdown.as_nanos() as u64);
+        put(&mut out, self.rules.grace_period.as_nanos() as u64);
+        put(&mut out, self.rules.result_duration.as_nanos() as u64);
+
+        let mut last = Duration::ZERO;
+        for (at, entry) in &self.entries {
+            put(&mut out, at.saturating_sub(last).as_nanos() as u64);
+            last = *at;
+            match entry {
+                Entry::Input(input) => match *input {
+                    Input::KeyDown => out.push(0),
+                    Input::MouseButtonDown => out.push(1),
+                    Input::MouseMotion { dx, dy } => {
+                        out.push(2);
+                        put_signed(&mut out, dx);
+                        put_signed(&mut out, dy);
+                    }
+                    Input::MouseWheel { x, y } => {
+                        out.push(3);
+                        put_signed(&mut out, x);
+                        put_signed(&mut out, y);
+                    }
+                    Input::ControllerButtonDown => out.push(4),
+                    Input::ControllerAxis { value } => {
+                        out.push(5);
+                        put_signed(&mut out, value as i32);
+                    }
+                    Input::Touch => out.push(6),
+                    Input::DeviceChanged => out.push(7),
+                },
+                Entry::Tick => out.push(TICK),
+                Entry::Offender(detail) => {
+                    out.push(OFFENDER);
+                    put(&mut out, detail.len() as u64);
+                    out.extend_from_slice(detail.as_bytes());
+                }
+                Entry::Pause => out.push(PAUSE),
+                Entry::Resume => out.push(RESUME),
+                Entry::FocusLost => out.push(FOCUS_LOST),
+                Entry::FocusGained => out.push(FOCUS_GAINED),
+                Entry::UnseenInput => out.push(UNSEEN_INPUT),
+                Entry::End => out.push(END),
+            }
+        }
+        out
+    }
+
+    pub fn decode(bytes: &[u8]) -> Result<Journal, String> {
+        let mut r = Reader { bytes, pos: 0 };
+        if r.take(4)? != MAGIC {
+            return Err("not a journal".to_string());
+        }
+        let version = r.byte()?;
+        if version != VERSION {
+            return Err(format!("unsupported journal version {}", version));
+        }
+        let flags = r.byte()?;
+        let started = UNIX_EPOCH + Duration::from_millis(r.number()?);
+        let rules = Rules {
+            sensitivity: Sensitivity {
+                motion_dead_zone: r.number_as("motion dead zone")?,
+                min_wheel_delta: r.number_as("wheel delta")?,
+                axis_threshold: r.number_as("axis threshold")?,
+                ignored: Reasons::from_bits(r.number_as("ignored devices")?),
+            },
+            countdown: Duration::from_nanos(r.number()?),
+            countdown_input: if flags & 4 != 0 {
+                CountdownInput::Restart
+            } else {
+                CountdownInput::Ignore
+            },
+            grace_period: Duration::from_nanos(r.number()?),
+            ignore_first_input: flags & 1 != 0,
+            result_duration: Duration::from_nanos(r.number()?),
+            wait_for_key: flags & 2 != 0,
+            focus: *FocusPolicy::ALL
+                .get((flags >> 3 & 3) as usize)
+                .ok_or_else(|| "unknown focus policy".to_string())?,
+        };
+
+        let mut entries = Vec::new();
+        let mut at = Duration::ZERO;
+        while r.pos < bytes.len() {
+            at += Duration::from_nanos(r.number()?);
+            let entry = match r.byte()? {
+                0 => Entry::Input(Input::KeyDown),
+                1 => Entry::Input(Input::MouseButtonDown),
+                2 => Entry::Input(Input::MouseMotion { dx: r.signed()?, dy: r.signed()? }),
+                3 => Entry::Input(Input::MouseWheel { x: r.signed()?, y: r.signed()? }),
+                4 => Entry::Input(Input::ControllerButtonDown),
+                5 => Entry::Input(Input::ControllerAxis {
+                    value: i16::try_from(r.signed()?).map_err(|_| "axis value out of range".to_string())?,
+                }),
+                6 => Entry::Input(Input::Touch),
+                7 => Entry::Input(Input::DeviceChanged),
+                TICK => Entry::Tick,
+                OFFENDER => {
+                    let len: usize = r.number_as("detail length")?;
+                    let detail = std::str::from_utf8(r.take(len)?).map_err(|_| "detail isn't UTF-8".to_string())?;
+                    Entry::Offender(detail.to_string())
+                }
+                PAUSE => Entry::Pause,
+                RESUME => Entry::Resume,
+                FOCUS_LOST => Entry::FocusLost,
+                FOCUS_GAINED => Entry::FocusGained,
+                UNSEEN_INPUT => Entry::UnseenInput,
+                END => Entry::End,
+                kind => return Err(format!("unknown entry kind {:#x} at byte {}", kind, r.pos - 1)),
+            };
+            entries.push((at, entry));
+        }
+        Ok(Journal {
+            started,
+            rules,
+            entries,
+        })
+    }
+}
+
+fn unix_ms(time: SystemTime) -> u64 {
+    time.duration_since(UNIX_EPOCH).unwrap_or_default().as_millis() as u64
+}
+
+fn put(out: &mut Vec<u8>, mut n: u64) {
+    while n >= 0x80 {
+        out.push(n as u8 | 0x80);
+        n >>= 7;
+    }
+    out.push(n as u8);
+}
+
+fn put_signed(out: &mut Vec<u8>, n: i32) {
+    put(out, ((n << 1) ^ (n >> 31)) as u32 as u64);
+}
+
+struct Reader<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+}
+
+impl Reader<'_> {
+    fn take(&mut self, n: usize) -> Result<&[u8], String> {
+        let end = self.pos.checked_add(n).ok_or_else(|| "journal is cut short".to_string())?;
+        let slice = self.bytes.get(self.pos..end).ok_or_else(|| "journal is cut short".to_string())?;
+        self.pos = end;
+        Ok(slice)
+    }
+
+    fn byte(&mut self) -> Result<u8, String> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn number(&mut self) -> Result<u64, String> {
+        let mut n = 0u64;
+        for shift in (0..64).step_by(7) {
+            let b = self.byte()?;
+            n |= ((b & 0x7f) as u64) << shift;
+            if b & 0x80 == 0 {
+                return Ok(n);
+            }
+        }
+        Err(format!("number too long at byte {}", self.pos))
+    }
+
+    fn number_as<T: TryFrom<u64>>(&mut self, what: &str) -> Result<T, String> {
+        T::try_from(self.number()?).map_err(|_| format!("{} out of range", what))
+    }
+
+    fn signed(&mut self) -> Result<i32, String> {
+        let n: u32 = self.number_as("value")?;
+        Ok((n >> 1) as i32 ^ -((n & 1) as i32))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn journal(rules: Rules) -> Journal {
+        Journal {
+            started: UNIX_EPOCH + Duration::from_millis(1_700_000_000_000),
+            rules,
+            entries: vec![
+                (Duration::ZERO, Entry::Input(Input::KeyDown)),
+                (Duration::from_millis(3), Entry::Input(Input::MouseMotion { dx: -4, dy: 9 })),
+                (Duration::from_secs(2), Entry::Offender("w".to_string())),
+                (Duration::from_secs(2), Entry::UnseenInput),
+                (Duration::from_secs(3), Entry::End),
+            ],
+        }
+    }
+
+    #[test]
+    fn round_trips() {
+        let rules = Rules {
+            countdown_input: CountdownInput::Restart,
+            focus: FocusPolicy::Ignore,
+            wait_for_key: true,
+            grace_period: Duration::from_millis(250),
+            ..Rules::default()
+        };
+        let original = journal(rules);
+        assert_eq!(Journal::decode(&original.encode()), Ok(original));
+    }
+
+    #[test]
+    fn other_versions_are_refused() {
+        let mut bytes = journal(Rules::default()).encode();
+        bytes[4] = VERSION + 1;
+        assert!(Journal::decode(&bytes).is_err());
+        bytes[4] = 0;
+        assert!(Journal::decode(&bytes).is_err());
+    }
+
+    #[test]
+    fn lengths_past_the_end_are_refused() {
+        let mut bytes = Journal {
+            entries: Vec::new(),
+            ..journal(Rules::default())
+        }
+        .encode();
+        put(&mut bytes, 0);
+        bytes.push(OFFENDER);
+        put(&mut bytes, u64::MAX);
+        assert_eq!(Journal::decode(&bytes), Err("journal is cut short".to_string()));
+    }
+}
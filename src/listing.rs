@@ -0,0 +1,211 @@
+use sdl2::render::RendererInfo;
+use sdl2::sys::SDL_RendererFlags;
+use sdl2::video::DisplayMode;
+use sdl2::VideoSubsystem;
+
+use crate::json;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    Table,
+    Json,
+}
+
+impl Format {
+    pub fn from_key(key: &str) -> Option<Format> {
+        match key {
+            "table" => Some(Format::Table),
+            "json" => Some(Format::Json),
+            _ => None,
+        }
+    }
+}
+
+/// Every render driver, numbered the way `--selected-gpu-renderer` expects.
+pub fn renderers(format: Format) -> String {
+    let drivers: Vec<RendererInfo> = sdl2::render::drivers().collect();
+    match format {
+        Format::Json => {
+            let items: Vec<json::Value> = drivers
+                .iter()
+                .enumerate()
+                .map(|(i, r)| {
+                    let mut fields = vec![("index", json::Value::from(i + 1))];
+                    fields.extend(renderer_fields(r));
+                    json::Value::object(fields)
+                })
+                .collect();
+            format!("{}\n", json::Value::object(vec![("renderers", items.into())]))
+        }
+        Format::Table => {
+            let rows = drivers
+                .iter()
+                .enumerate()
+                .map(|(i, r)| {
+                    vec![
+                        (i + 1).to_string(),
+                        r.name.to_string(),
+                        renderer_flags(r.flags).join(", "),
+                        format!("{}x{}", r.max_texture_width, r.max_texture_height),
+                        texture_formats(r).join(", "),
+                    ]
+                })
+                .collect();
+            table(&["#", "Name", "Flags", "Max texture", "Texture formats"], rows)
+        }
+    }
+}
+
+pub fn renderer_json(r: &RendererInfo) -> json::Value {
+    json::Value::object(renderer_fields(r))
+}
+
+/// Everything in a [`RendererInfo`].
+fn renderer_fields(r: &RendererInfo) -> Vec<(&'static str, json::Value)> {
+    vec![
+        ("name", r.name.into()),
+        ("flags", renderer_flags(r.flags).into()),
+        ("flag_bits", r.flags.into()),
+        ("texture_formats", texture_formats(r).into()),
+        ("max_texture_width", r.max_texture_width.into()),
+        ("max_texture_height", r.max_texture_height.into()),
+    ]
+}
+
+/// The `SDL_RendererFlags` that are set, by name.
+pub fn renderer_flags(flags: u32) -> Vec<&'static str> {
+    [
+        (SDL_RendererFlags::SDL_RENDERER_SOFTWARE, "software"),
+        (SDL_RendererFlags::SDL_RENDERER_ACCELERATED, "accelerated"),
+        (SDL_RendererFlags::SDL_RENDERER_PRESENTVSYNC, "present_vsync"),
+        (SDL_RendererFlags::SDL_RENDERER_TARGETTEXTURE, "target_texture"),
+    ]
+    .into_iter()
+    .filter(|(bit, _)| flags & *bit as u32 != 0)
+    .map(|(_, name)| name)
+    .collect()
+}
+
+fn texture_formats(r: &RendererInfo) -> Vec<String> {
+    r.texture_formats.iter().map(|f| format!("{:?}", f)).collect()
+}
+
+/// Every display, numbered the way `--display` expects, with all the modes it supports.
+pub fn displays(video: &VideoSubsystem, format: Format) -> String {
+    let count = video.num_video_displays().unwrap_or(0);
+    match format {
+        Format::Json => {
+            let items: Vec<json::Value> = (0..count).map(|i| display_json(video, i)).collect();
+            format!("{}\n", json::Value::object(vec![("displays", items.into())]))
+        }
+        Format::Table => {
+            let mut displays = Vec::new();
+            let mut modes = Vec::new();
+            for i in 0..count {
+                let rect = |r: Result<sdl2::rect::Rect, String>| match r {
+                    Ok(b) => format!("{}x{} at ({}, {})", b.width(), b.height(), b.x(), b.y()),
+                    Err(_) => "-".to_string(),
+                };
+                let dpi = match video.display_dpi(i) {
+                    Ok((d, h, v)) => format!("{:.0} ({:.0}x{:.0})", d, h, v),
+                    Err(_) => "-".to_string(),
+                };
+                let mode = |m: Result<DisplayMode, String>| match m {
+                    Ok(m) => format!("{}x{} @ {}Hz", m.w, m.h, m.refresh_rate),
+                    Err(_) => "-".to_string(),
+                };
+                displays.push(vec![
+                    (i + 1).to_string(),
+                    video.display_name(i).unwrap_or_default(),
+                    rect(video.display_bounds(i)),
+                    rect(video.display_usable_bounds(i)),
+                    dpi,
+                    mode(video.desktop_display_mode(i)),
+                    mode(video.current_display_mode(i)),
+                ]);
+                for m in display_modes(video, i) {
+                    modes.push(vec![
+                        (i + 1).to_string(),
+                        format!("{}x{}", m.w, m.h),
+                        format!("{}Hz", m.refresh_rate),
+                        format!("{:?}", m.format),
+                    ]);
+                }
+            }
+            let displays = table(
+                &["#", "Name", "Bounds", "Usable bounds", "DPI", "Desktop mode", "Current mode"],
+                displays,
+            );
+            let modes = table(&["Display", "Resolution", "Refresh", "Format"], modes);
+            format!("{}\n{}", displays, modes)
+        }
+    }
+}
+
+fn display_json(video: &VideoSubsystem, i: i32) -> json::Value {
+    let rect = |r: Result<sdl2::rect::Rect, String>| match r {
+        Ok(b) => json::Value::object(vec![
+            ("x", b.x().into()),
+            ("y", b.y().into()),
+            ("width", b.width().into()),
+            ("height", b.height().into()),
+        ]),
+        Err(_) => json::Value::Null,
+    };
+    let dpi = match video.display_dpi(i) {
+        Ok((d, h, v)) => json::Value::object(vec![
+            ("diagonal", f64::from(d).into()),
+            ("horizontal", f64::from(h).into()),
+            ("vertical", f64::from(v).into()),
+        ]),
+        Err(_) => json::Value::Null,
+    };
+    let modes: Vec<json::Value> = display_modes(video, i).iter().map(mode_json).collect();
+    json::Value::object(vec![
+        ("index", (i + 1).into()),
+        ("name", video.display_name(i).ok().into()),
+        ("bounds", rect(video.display_bounds(i))),
+        ("usable_bounds", rect(video.display_usable_bounds(i))),
+        ("dpi", dpi),
+        ("desktop_mode", video.desktop_display_mode(i).ok().as_ref().map(mode_json).into()),
+        ("current_mode", video.current_display_mode(i).ok().as_ref().map(mode_json).into()),
+        ("modes", modes.into()),
+    ])
+}
+
+fn display_modes(video: &VideoSubsystem, i: i32) -> Vec<DisplayMode> {
+    (0..video.num_display_modes(i).unwrap_or(0))
+        .filter_map(|m| video.display_mode(i, m).ok())
+        .collect()
+}
+
+fn mode_json(m: &DisplayMode) -> json::Value {
+    json::Value::object(vec![
+        ("width", m.w.into()),
+        ("height", m.h.into()),
+        ("refresh_rate", m.refresh_rate.into()),
+        ("format", format!("{:?}", m.format).into()),
+    ])
+}
+
+/// Left aligned columns, two spaces apart.
+fn table(header: &[&str], rows: Vec<Vec<String>>) -> String {
+    let mut widths: Vec<usize> = header.iter().map(|h| h.chars().count()).collect();
+    for row in &rows {
+        for (w, cell) in widths.iter_mut().zip(row) {
+            *w = (*w).max(cell.chars().count());
+        }
+    }
+    let header: Vec<String> = header.iter().map(|h| h.to_string()).collect();
+    let mut out = String::new();
+    for row in std::iter::once(&header).chain(&rows) {
+        let cells: Vec<String> = row
+            .iter()
+            .zip(&widths)
+            .map(|(cell, w)| format!("{:<1$}", cell, w))
+            .collect();
+        out.push_str(cells.join("  ").trim_end());
+        out.push('\n');
+    }
+    out
+}
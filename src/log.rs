@@ -0,0 +1,141 @@
+use std::io::Write;
+use std::path::Path;
+use std::sync::{Mutex, OnceLock};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Level {
+    Error,
+    Warn,
+    Info,
+    /// Every input event and frame limiter hiccup.
+    Debug,
+    /// Every frame.
+    Trace,
+}
+
+impl Level {
+    pub const ALL: [Level; 5] = [Level::Error, Level::Warn, Level::Info, Level::Debug, Level::Trace];
+
+    pub fn key(&self) -> &'static str {
+        match self {
+            Level::Error => "error",
+            Level::Warn => "warn",
+            Level::Info => "info",
+            Level::Debug => "debug",
+            Level::Trace => "trace",
+        }
+    }
+
+    pub fn from_key(key: &str) -> Option<Level> {
+        Level::ALL.into_iter().find(|l| l.key() == key)
+    }
+}
+
+struct Logger {
+    level: Level,
+    file: Option<Mutex<std::fs::File>>,
+}
+
+static LOGGER: OnceLock<Logger> = OnceLock::new();
+
+/// Until this is called everything from info up goes to stderr.
+pub fn init(level: Level, file: Option<&Path>) -> std::io::Result<()> {
+    let file = match file {
+        Some(path) => Some(Mutex::new(
+            std::fs::OpenOptions::new().create(true).append(true).open(path)?,
+        )),
+        None => None,
+    };
+    let _ = LOGGER.set(Logger { level, file });
+    Ok(())
+}
+
+pub fn enabled(level: Level) -> bool {
+    level <= LOGGER.get().map_or(Level::Info, |l| l.level)
+}
+
+/// One line per entry: `<UTC time> <LEVEL> <target> <message>`, to stderr and the log file.
+pub fn write(level: Level, target: &str, args: std::fmt::Arguments) {
+    if !enabled(level) {
+        return;
+    }
+    let line = format!(
+        "{} {:<5} {} {}",
+        timestamp(SystemTime::now()),
+        level.key().to_uppercase(),
+        target,
+        args
+    );
+    eprintln!("{}", line);
+    if let Some(file) = LOGGER.get().and_then(|l| l.file.as_ref()) {
+        if let Ok(mut file) = file.lock() {
+            let _ = writeln!(file, "{}", line);
+        }
+    }
+}
+
+/// RFC 3339 in UTC with milliseconds, e.g. `2024-05-01T13:37:00.000Z`.
+fn timestamp(time: SystemTime) -> String {
+    let since_epoch = time.duration_since(UNIX_EPOCH).unwrap_or_default();
+    let secs = since_epoch.as_secs();
+    let (year, month, day) = civil_from_days((secs / 86_400) as i64);
+    let rem = secs % 86_400;
+    format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}.{:03}Z",
+        year,
+        month,
+        day,
+        rem / 3600,
+        rem / 60 % 60,
+        rem % 60,
+        since_epoch.subsec_millis()
+    )
+}
+
+/// Days since 1970-01-01 to a (year, month, day) date, Howard Hinnant's algorithm.
+fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = (doy - (153 * mp + 2) / 5 + 1) as u32;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+    let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+    (year, month, day)
+}
+
+macro_rules! error {
+    ($target:expr, $($arg:tt)+) => {
+        $crate::log::write($crate::log::Level::Error, $target, format_args!($($arg)+))
+    };
+}
+
+macro_rules! warn_ {
+    ($target:expr, $($arg:tt)+) => {
+        $crate::log::write($crate::log::Level::Warn, $target, format_args!($($arg)+))
+    };
+}
+
+macro_rules! info {
+    ($target:expr, $($arg:tt)+) => {
+        $crate::log::write($crate::log::Level::Info, $target, format_args!($($arg)+))
+    };
+}
+
+macro_rules! debug {
+    ($target:expr, $($arg:tt)+) => {
+        $crate::log::write($crate::log::Level::Debug, $target, format_args!($($arg)+))
+    };
+}
+
+macro_rules! trace {
+    ($target:expr, $($arg:tt)+) => {
+        $crate::log::write($crate::log::Level::Trace, $target, format_args!($($arg)+))
+    };
+}
+
+// a macro called `warn` can't be re-exported as is, it clashes with the `#[warn]` attribute
+pub(crate) use {debug, error, info, trace, warn_ as warn};
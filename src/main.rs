@@ -1,33 +1,37 @@
-
-use std::time::Instant;
-
 use clap::Parser;
-use ctrlc;
-use sdl2::{self, render::Canvas};
 // use winit;
-use msgbox;
 
-mod fps_capper;
+mod benchmark;
+mod chord;
+mod config;
+mod controllers;
+mod countdown;
+mod error;
+mod evdev;
+mod forensics;
+mod frame_graph;
+mod frame_limiter;
+mod frame_stats;
+mod headless;
+mod history;
+mod idle;
+mod journal;
+mod json;
+mod listing;
+mod log;
+mod paths;
+mod renderer;
+mod sensitivity;
+mod session;
+mod text;
 mod windows_quirks;
 
-#[derive(Debug)]
-struct CustomError {
-    msg: String,
-}
-impl std::fmt::Display for CustomError {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        f.write_str(&self.msg).unwrap();
-        Ok(())
-    }
-}
-impl std::error::Error for CustomError {}
-
 #[derive(clap::Parser)]
 #[command(author = "timelessnesses", about = "Nothing")]
 struct Cli {
     /// Frame limiting
-    #[arg(short, long)]
-    fps: Option<u64>,
+    #[arg(short, long, value_parser = clap::value_parser!(u32).range(1..))]
+    fps: Option<u32>,
     /// List GPU renderers (for the SELECTED_GPU_RENDERER arg)
     #[arg(short, long)]
     list_gpu_renderers: bool,
@@ -39,18 +43,333 @@ struct Cli {
     #[arg(short, long)]
     vsync: Option<bool>,
 
-    /// Force Unlimited FPS
-    #[arg(short, long)]
-    unlimited: bool
+    /// Force Unlimited FPS, --unlimited=false turns it off again
+    #[arg(short, long, num_args = 0..=1, require_equals = true, default_missing_value = "true")]
+    unlimited: Option<bool>,
+
+    /// How frames get paced: sleep, spin, hybrid (sleep then spin) or deadline (hybrid without drift)
+    #[arg(long, value_name = "STRATEGY", value_parser = parse_limiter)]
+    limiter: Option<frame_limiter::Strategy>,
+
+    /// fullscreen, desktop (fullscreen at desktop resolution), windowed or borderless
+    #[arg(long, value_name = "MODE", value_parser = parse_window_mode)]
+    window_mode: Option<config::WindowMode>,
+
+    /// Window size (or the fullscreen resolution), e.g. 1280x720
+    #[arg(long, value_name = "WxH", value_parser = config::parse_size)]
+    size: Option<(u32, u32)>,
+
+    /// Which monitor to open on (for the LIST_DISPLAYS arg)
+    #[arg(long, value_name = "N")]
+    display: Option<usize>,
+
+    /// List displays (for the DISPLAY arg)
+    #[arg(long)]
+    list_displays: bool,
+
+    /// How --list-gpu-renderers and --list-displays print: table or json
+    #[arg(long, value_name = "FORMAT", default_value = "table", value_parser = parse_format)]
+    format: listing::Format,
+
+    /// Seconds counted down before a run starts, 0 starts it right away
+    #[arg(long, value_name = "SECONDS", value_parser = parse_duration)]
+    countdown: Option<std::time::Duration>,
+
+    /// What input during the countdown does: ignore or restart
+    #[arg(long, value_name = "ACTION", value_parser = parse_countdown_input)]
+    countdown_input: Option<session::CountdownInput>,
+
+    /// How the countdown looks: digits, fade or bar
+    #[arg(long, value_name = "STYLE", value_parser = parse_countdown_style)]
+    countdown_style: Option<countdown::Style>,
+
+    /// Keys that pause and resume a run, e.g. ctrl+shift+p
+    #[arg(long, value_name = "CHORD", value_parser = parse_chord)]
+    pause_chord: Option<chord::Chord>,
+
+    /// Turn pausing off
+    #[arg(long, conflicts_with = "pause_chord")]
+    no_pause: bool,
+
+    /// What losing focus, minimising or hiding the window does to a run: fail, pause or ignore
+    /// (the run keeps going but is marked as played in the background)
+    #[arg(long, value_name = "POLICY", value_parser = parse_focus_policy)]
+    focus_policy: Option<session::FocusPolicy>,
+
+    /// Seconds after starting a run during which input is ignored
+    #[arg(long, value_name = "SECONDS", value_parser = parse_duration)]
+    grace_period: Option<std::time::Duration>,
+
+    /// Don't swallow the first input of each run (usually the release of the starting press)
+    #[arg(long, num_args = 0..=1, require_equals = true, default_missing_value = "true")]
+    count_first_input: Option<bool>,
+
+    /// Seconds the result stays on screen
+    #[arg(long, value_name = "SECONDS", value_parser = parse_duration)]
+    result_duration: Option<std::time::Duration>,
+
+    /// Keep the result on screen until a key is pressed
+    #[arg(long, num_args = 0..=1, require_equals = true, default_missing_value = "true")]
+    wait_for_key: Option<bool>,
+
+    /// Pixels the mouse can drift during a run before it counts as movement
+    #[arg(long, value_name = "PIXELS")]
+    motion_dead_zone: Option<u32>,
+
+    /// Smallest mouse wheel step that counts as movement
+    #[arg(long, value_name = "STEPS")]
+    min_wheel_delta: Option<u32>,
+
+    /// How far (out of 32767) a controller stick or trigger can move before it counts
+    #[arg(long, value_name = "VALUE")]
+    axis_threshold: Option<u16>,
+
+    /// Input that never fails a run (keyboard, mouse_button, mouse_motion, mouse_wheel,
+    /// controller_button, controller_axis, touch, device_hotplug), can be repeated
+    #[arg(long, value_name = "DEVICE", value_parser = parse_reason)]
+    ignore: Vec<session::Reason>,
+
+    /// Config file to use instead of the one in your config directory
+    #[arg(long, value_name = "FILE")]
+    config: Option<std::path::PathBuf>,
+
+    /// Print the effective configuration (defaults, config file, environment and flags merged) and exit
+    #[arg(long)]
+    print_config: bool,
+
+    /// How much gets logged: error, warn, info, debug (every input event) or trace (every frame)
+    #[arg(long, value_name = "LEVEL", value_parser = parse_log_level)]
+    log_level: Option<log::Level>,
+
+    /// Append the log to this file as well as printing it
+    #[arg(long, value_name = "FILE")]
+    log_file: Option<std::path::PathBuf>,
+
+    /// Where fatal errors are reported: msgbox, stderr, or a file to append them to
+    #[arg(long, value_name = "SINK", value_parser = parse_sink)]
+    errors: Option<error::Sink>,
+
+    /// Run the render loop for this many seconds, then write a frame timing report and exit
+    #[arg(long, value_name = "SECONDS", value_parser = parse_seconds)]
+    benchmark: Option<std::time::Duration>,
+
+    /// Where the benchmark report goes, as PATH.json and PATH.csv
+    #[arg(long, value_name = "PATH", default_value = "benchmark")]
+    benchmark_report: std::path::PathBuf,
+
+    /// Also read /dev/input (Linux only), so input that goes to other windows counts while the
+    /// window is in the background
+    #[arg(long, num_args = 0..=1, require_equals = true, default_missing_value = "true")]
+    evdev: Option<bool>,
+
+    /// Track a run from /dev/input without opening a window (Linux only), printing it as JSON once it fails
+    #[arg(long)]
+    monitor: bool,
+
+    /// Where the OS's idle time comes from, to flag runs it disagrees with: auto, x11, dbus, windows or off
+    #[arg(long, value_name = "SOURCE", value_parser = parse_idle_source)]
+    idle_source: Option<idle::Source>,
+
+    /// Save a journal of every input of every run, for --replay
+    #[arg(long, num_args = 0..=1, require_equals = true, default_missing_value = "true")]
+    record_journal: Option<bool>,
+
+    /// Play a run journal back through the tracking rules and print how it ended as JSON
+    #[arg(long, value_name = "JOURNAL")]
+    replay: Option<std::path::PathBuf>,
+
+    /// Run without a window, feeding the events from a timeline file and printing the runs as JSON
+    #[arg(long, value_name = "TIMELINE")]
+    headless: Option<std::path::PathBuf>,
+}
+
+fn parse_reason(key: &str) -> Result<session::Reason, String> {
+    let devices = session::Reason::ALL.into_iter().filter(|r| *r != session::Reason::FocusLost);
+    devices.clone().find(|r| r.key() == key).ok_or_else(|| {
+        let keys: Vec<&str> = devices.map(|r| r.key()).collect();
+        format!("expected one of {}", keys.join(", "))
+    })
+}
+
+fn parse_focus_policy(key: &str) -> Result<session::FocusPolicy, String> {
+    session::FocusPolicy::from_key(key).ok_or_else(|| "expected one of fail, pause, ignore".to_string())
+}
+
+fn parse_idle_source(key: &str) -> Result<idle::Source, String> {
+    idle::Source::from_key(key).ok_or_else(|| "expected one of auto, x11, dbus, windows, off".to_string())
+}
+
+fn parse_seconds(s: &str) -> Result<std::time::Duration, String> {
+    s.parse::<f64>()
+        .ok()
+        .filter(|secs| *secs > 0.0)
+        .and_then(|secs| std::time::Duration::try_from_secs_f64(secs).ok())
+        .ok_or_else(|| format!("expected a positive number of seconds, got `{}`", s))
+}
+
+/// Zero included, unlike [`parse_seconds`].
+fn parse_duration(s: &str) -> Result<std::time::Duration, String> {
+    s.parse::<f64>()
+        .ok()
+        .and_then(|secs| std::time::Duration::try_from_secs_f64(secs).ok())
+        .ok_or_else(|| format!("expected a number of seconds, got `{}`", s))
+}
+
+fn parse_countdown_input(key: &str) -> Result<session::CountdownInput, String> {
+    session::CountdownInput::from_key(key).ok_or_else(|| "expected ignore or restart".to_string())
+}
+
+fn parse_countdown_style(key: &str) -> Result<countdown::Style, String> {
+    countdown::Style::from_key(key).ok_or_else(|| "expected one of digits, fade, bar".to_string())
+}
+
+fn parse_chord(key: &str) -> Result<chord::Chord, String> {
+    chord::Chord::from_key(key)
+        .ok_or_else(|| "expected modifiers (ctrl, shift, alt, gui) and a key joined by +".to_string())
+}
+
+fn parse_log_level(key: &str) -> Result<log::Level, String> {
+    log::Level::from_key(key).ok_or_else(|| "expected one of error, warn, info, debug, trace".to_string())
 }
 
-fn report_error(error: impl std::error::Error, title: &str) {
-    msgbox::create(title, &error.to_string(), msgbox::IconType::Error).unwrap();
-    panic!("{}", error)
+fn parse_sink(key: &str) -> Result<error::Sink, String> {
+    error::Sink::from_key(key).ok_or_else(|| "expected msgbox, stderr or a file path".to_string())
+}
+
+fn parse_format(key: &str) -> Result<listing::Format, String> {
+    listing::Format::from_key(key).ok_or_else(|| "expected table or json".to_string())
+}
+
+fn parse_limiter(key: &str) -> Result<frame_limiter::Strategy, String> {
+    frame_limiter::Strategy::from_key(key)
+        .ok_or_else(|| "expected one of sleep, spin, hybrid, deadline".to_string())
+}
+
+fn parse_window_mode(key: &str) -> Result<config::WindowMode, String> {
+    config::WindowMode::from_key(key)
+        .ok_or_else(|| "expected one of fullscreen, desktop, windowed, borderless".to_string())
+}
+
+/// Defaults, then the config file, then `NOTHING_*` environment variables, then flags.
+fn load_config(cli: &Cli) -> Result<config::Config, String> {
+    let mut config = config::Config::default();
+    match &cli.config {
+        Some(path) if !path.exists() => {
+            return Err(format!("config file {} does not exist", path.display()))
+        }
+        Some(path) => config.merge_file(path)?,
+        None => {
+            if let Some(path) = config::Config::default_path() {
+                config.merge_file(&path)?;
+            }
+        }
+    }
+    config.merge_env(std::env::vars())?;
+
+    if let Some(fps) = cli.fps {
+        config.fps = fps;
+    }
+    if let Some(unlimited) = cli.unlimited {
+        config.unlimited = unlimited;
+    }
+    if let Some(limiter) = cli.limiter {
+        config.limiter = limiter;
+    }
+    if let Some(vsync) = cli.vsync {
+        config.vsync = vsync;
+    }
+    if let Some(renderer) = cli.selected_gpu_renderer {
+        config.selected_gpu_renderer = Some(renderer);
+    }
+    if let Some(mode) = cli.window_mode {
+        config.window_mode = mode;
+    }
+    if let Some(size) = cli.size {
+        config.size = size;
+    }
+    if let Some(display) = cli.display {
+        config.display = Some(display);
+    }
+    if let Some(countdown) = cli.countdown {
+        config.countdown = countdown;
+    }
+    if let Some(input) = cli.countdown_input {
+        config.countdown_input = input;
+    }
+    if let Some(style) = cli.countdown_style {
+        config.countdown_style = style;
+    }
+    if let Some(chord) = &cli.pause_chord {
+        config.pause_chord = Some(chord.clone());
+    }
+    if cli.no_pause {
+        config.pause_chord = None;
+    }
+    if let Some(policy) = cli.focus_policy {
+        config.focus_policy = policy;
+    }
+    if let Some(grace) = cli.grace_period {
+        config.grace_period = grace;
+    }
+    if let Some(count) = cli.count_first_input {
+        config.ignore_first_input = !count;
+    }
+    if let Some(duration) = cli.result_duration {
+        config.result_duration = duration;
+    }
+    if let Some(wait) = cli.wait_for_key {
+        config.wait_for_key = wait;
+    }
+    if let Some(dead_zone) = cli.motion_dead_zone {
+        config.motion_dead_zone = dead_zone;
+    }
+    if let Some(delta) = cli.min_wheel_delta {
+        config.min_wheel_delta = delta;
+    }
+    if let Some(threshold) = cli.axis_threshold {
+        config.axis_threshold = threshold;
+    }
+    if let Some(evdev) = cli.evdev {
+        config.evdev = evdev;
+    }
+    if let Some(source) = cli.idle_source {
+        config.idle_source = source;
+    }
+    if let Some(record) = cli.record_journal {
+        config.record_journal = record;
+    }
+    if let Some(level) = cli.log_level {
+        config.log_level = level;
+    }
+    if let Some(file) = &cli.log_file {
+        config.log_file = Some(file.clone());
+    }
+    if let Some(sink) = &cli.errors {
+        config.errors = sink.clone();
+    }
+    if !cli.ignore.is_empty() {
+        config.ignore = Default::default();
+        for device in &cli.ignore {
+            config.ignore.insert(*device);
+        }
+    }
+    Ok(config)
 }
 
 const ROBOTO: &[u8; 167000] = include_bytes!("assets/Roboto-Light.ttf");
 
+/// Falls back to the bundled Roboto when no font file is configured.
+fn load_font<'ttf>(
+    font_ctx: &'ttf sdl2::ttf::Sdl2TtfContext,
+    path: Option<&std::path::Path>,
+    size: u16,
+) -> Result<sdl2::ttf::Font<'ttf, 'static>, String> {
+    match path {
+        Some(p) => font_ctx.load_font(p, size),
+        None => font_ctx.load_font_from_rwops(sdl2::rwops::RWops::from_bytes(ROBOTO)?, size),
+    }
+}
+
 fn main() {
     #[cfg(target_os = "windows")]
     {
@@ -60,442 +379,779 @@ fn main() {
         }
     }
     let parsed = Cli::parse();
-    if parsed.list_gpu_renderers {
-        for (i, item) in sdl2::render::drivers().enumerate() {
-            let mut flags = vec![];
-            if item.flags & 0x01 > 0 {
-                flags.push("Software Rendered");
-            }
-            if item.flags & 0x02 > 0 {
-                flags.push("Renderer Accelerated");
-            }
-            if item.flags & 0x04 > 0 {
-                flags.push("VSynced");
-            }
-            if item.flags & 0x08 > 0 {
-                flags.push("Support rendering into a texture");
-            }
-            println!(
-                "Renderer #{}:\n   Name: {}\n  Flags: {}",
-                i + 1,
-                item.name,
-                flags.join(", ")
-            )
+    let config = match load_config(&parsed) {
+        Ok(c) => c,
+        Err(e) => {
+            // the config says where errors go, so this one can only go to stderr
+            let e = error::Error::Config(e);
+            error::Sink::Stderr.report(&e);
+            std::process::exit(e.exit_code());
         }
-        return;
+    };
+    if let Err(e) = log::init(config.log_level, config.log_file.as_deref()) {
+        let e = error::Error::Io(format!("Failed to open the log file {:?}", config.log_file), e);
+        config.errors.report(&e);
+        std::process::exit(e.exit_code());
+    }
+    if let Err(e) = run(&parsed, &config) {
+        log::error!("main", "{} exit={}", e, e.exit_code());
+        config.errors.report(&e);
+        std::process::exit(e.exit_code());
     }
+}
 
-    let fl = match parsed.fps {
-        Some(f) => f,
-        None => 60,
-    };
-    let ctx = sdl2::init().unwrap();
-    let video = ctx.video().unwrap();
+fn run(parsed: &Cli, config: &config::Config) -> Result<(), error::Error> {
+    if parsed.print_config {
+        print!("{}", config.to_toml());
+        return Ok(());
+    }
+    if let Some(timeline) = &parsed.headless {
+        return headless::run(timeline, config.rules());
+    }
+    if let Some(path) = &parsed.replay {
+        let journal = journal::Journal::load(path)
+            .map_err(|e| error::Error::Io(format!("Failed to read {}", path.display()), e))?;
+        let (session, forensics) = journal.replay();
+        let reasons: Vec<&str> = session.reasons().iter().map(|r| r.key()).collect();
+        let started = journal.started.duration_since(std::time::UNIX_EPOCH).unwrap_or_default();
+        println!(
+            "{}",
+            json::Value::object(vec![
+                ("started", (started.as_millis() as u64).into()),
+                ("phase", format!("{:?}", session.phase()).to_lowercase().into()),
+                ("inactive_for", session.elapsed().as_secs_f64().into()),
+                ("paused_for", session.paused().as_secs_f64().into()),
+                ("reasons", reasons.into()),
+                ("background", session.background().into()),
+                ("idle_mismatch", session.idle_mismatch().into()),
+                ("text", status_text(&session).into()),
+                ("forensics", forensics.to_json()),
+            ])
+        );
+        return Ok(());
+    }
+    if parsed.list_gpu_renderers {
+        print!("{}", listing::renderers(parsed.format));
+        return Ok(());
+    }
+    let shutdown = shutdown_flag();
+    if parsed.monitor {
+        return evdev::run(std::path::Path::new(evdev::DEV_INPUT), config.rules(), &shutdown);
+    }
+
+    let ctx = sdl2::init().map_err(|e| error::Error::Init("SDL", e))?;
+    let video = ctx.video().map_err(|e| error::Error::Init("video", e))?;
+
+    if parsed.list_displays {
+        print!("{}", listing::displays(&video, parsed.format));
+        return Ok(());
+    }
 
     let window = {
-        match video
-            .window("Nothing", 800, 600)
-            .position_centered()
+        let (w, h) = config.size;
+        let mut builder = video.window("Nothing", w, h);
+        match config.display {
+            Some(d) if d >= 1 && (d as i32) <= video.num_video_displays().unwrap_or(0) => {
+                // SDL_WINDOWPOS_CENTERED_DISPLAY(d - 1)
+                let pos = (sdl2::sys::SDL_WINDOWPOS_CENTERED_MASK | (d as u32 - 1)) as i32;
+                builder.position(pos, pos)
+            }
+            Some(d) => {
+                return Err(error::Error::Window(format!(
+                    "there is no display #{}, see --list-displays",
+                    d
+                )))
+            }
+            None => builder.position_centered(),
+        };
+        builder
             .allow_highdpi()
-            // .resizable()
             .metal_view()
-            .opengl()
-            .fullscreen()
-            .fullscreen_desktop()
-            .build()
-        {
-            Ok(w) => Some(w),
-            Err(e) => {
-                report_error(e, "Failed to initialize SDL2 window");
-                None
-            }
-        }
-    }
-    .unwrap();
+            .opengl();
+        match config.window_mode {
+            config::WindowMode::Fullscreen => builder.fullscreen(),
+            config::WindowMode::Desktop => builder.fullscreen_desktop(),
+            config::WindowMode::Windowed => builder.resizable(),
+            config::WindowMode::Borderless => builder.borderless().resizable(),
+        };
+        builder.build().map_err(|e| error::Error::Window(e.to_string()))?
+    };
 
-    let mut running = true;
-    let vsync = {
-        match parsed.vsync {
-            Some(t) => t,
-            None => false
+    let mut canvas = renderer::create_canvas(window, config.selected_gpu_renderer, config.vsync)
+        .map_err(error::Error::Renderer)?;
+    let renderer_line = {
+        let info = canvas.info();
+        format!("Renderer: {} ({})", info.name, listing::renderer_flags(info.flags).join(", "))
+    };
+    canvas.set_draw_color(config.background);
+    let mut controllers = match controllers::Controllers::init(&ctx) {
+        Ok(c) => Some(c),
+        Err(e) => {
+            log::warn!("controllers", "failed to initialize controller support, controllers won't be tracked: {}", e);
+            None
         }
     };
-    let mut canvas: Canvas<sdl2::video::Window>;
-    if !vsync {    
-        canvas = match parsed.selected_gpu_renderer {
-            Some(i) => match window.into_canvas().index((i - 1) as u32).accelerated().build() {
-                Ok(c) => Some(c),
-                Err(e) => {
-                    report_error(e, "Failed to build Canvas");
-                    None
-                }
-            },
-            None => match window.into_canvas().accelerated().build() {
-                Ok(c) => Some(c),
-                Err(e) => {
-                    report_error(e, "Failed to build default Canvas");
-                    None
-                }
-            },
-        }.unwrap();
+    let mut event_pump = ctx.event_pump().map_err(|e| error::Error::Init("events", e))?;
+    let mut late = std::time::Duration::ZERO; // how late the last frame woke up
+    // SDL only sees input while the window has focus, the devices fill in the rest
+    let mut devices = if config.evdev {
+        Some(evdev::Monitor::open(std::path::Path::new(evdev::DEV_INPUT))?)
     } else {
-        canvas = match parsed.selected_gpu_renderer {
-            Some(i) => match window.into_canvas().index((i - 1) as u32).accelerated().present_vsync().build() {
-                Ok(c) => Some(c),
-                Err(e) => {
-                    report_error(e, "Failed to build Canvas");
-                    None
-                }
-            },
-            None => match window.into_canvas().accelerated().present_vsync().build() {
-                Ok(c) => Some(c),
-                Err(e) => {
-                    report_error(e, "Failed to build default Canvas");
-                    None
-                }
-            },
-        }.unwrap();
-    }
-    let mut event_pump = {
-        match ctx.event_pump() {
-            Ok(e) => Some(e),
-            Err(e) => {
-                report_error(CustomError { msg: e }, "Failed to initialize EventPump");
-                None
-            }
-        }
+        None
+    };
+    let mut activity = Vec::new();
+    let mut idle = idle::IdleMonitor::start(config.idle_source);
+    match &idle {
+        Some(m) => log::info!("idle", "checking runs against the idle time from {}", m.source().key()),
+        None => log::info!("idle", "no idle time from {}, runs aren't checked against it", config.idle_source.key()),
     }
-    .unwrap();
-    let mut capper = fps_capper::FpsLimiter::new({
-        if parsed.unlimited {
-            -1 as i32
-        } else {
-            fl as i32
-        }
-    });
-    let _ = ctrlc::set_handler(move || {
-        running = !running;
-    });
+    // the last time any input came in, whether or not it counted
+    let mut input_seen = std::time::Instant::now();
 
     // let mut draw_intro_text = false;
-    let font_ctx = match sdl2::ttf::init() {
-        Ok(f) => Some(f),
-        Err(e) => {
-            report_error(e, "Failed to initialize TTF Context");
-            None
-        }
-    }
-    .unwrap();
+    let font_ctx = sdl2::ttf::init().map_err(|e| error::Error::Init("SDL_ttf", e.to_string()))?;
     let ROBOTO_font =
-        match font_ctx.load_font_from_rwops(sdl2::rwops::RWops::from_bytes(ROBOTO).unwrap(), 45) {
-            Ok(f) => Some(f),
-            Err(e) => {
-                report_error(CustomError { msg: e }, "Failed to load ROBOTO UI font");
-                None
-            }
-        }
-        .unwrap();
+        load_font(&font_ctx, config.font.as_deref(), config.font_size).map_err(error::Error::Font)?;
+
+    let countdown_font = load_font(&font_ctx, config.font.as_deref(), config.font_size.saturating_mul(3))
+        .map_err(error::Error::Font)?;
 
     let fps_font =
-        match font_ctx.load_font_from_rwops(sdl2::rwops::RWops::from_bytes(ROBOTO).unwrap(), 15) {
-            Ok(f) => Some(f),
+        load_font(&font_ctx, config.font.as_deref(), config.hud_font_size).map_err(error::Error::Font)?;
+
+    let tc = canvas.texture_creator();
+
+    let (mut width, mut height) = canvas.output_size().map_err(|e| error::Error::Render("the window", e))?;
+    let mut ui_text =
+        text::GlyphAtlas::new(&ROBOTO_font, &tc, config.foreground).map_err(|e| error::Error::Render("text", e))?;
+    let mut countdown_text = text::GlyphAtlas::new(&countdown_font, &tc, config.foreground)
+        .map_err(|e| error::Error::Render("text", e))?;
+    let mut hud_text =
+        text::GlyphAtlas::new(&fps_font, &tc, config.foreground).map_err(|e| error::Error::Render("text", e))?;
+    let mut intro_label = text::Label::default();
+    let mut best_label = text::Label::default();
+    let mut status_label = text::Label::default();
+    let mut countdown_label = text::Label::default();
+    let mut hud_labels: [text::Label; 5] = Default::default();
+    let mut forensics_labels: [text::Label; 2] = Default::default();
+    let mut pause_hint_label = text::Label::default();
+    let mut pause_chord = chord::ChordFilter::new(config.pause_chord.clone());
+
+    // only moves when set, so the journal and the session agree on when everything happened
+    let clock = session::ManualClock::new(std::time::Instant::now());
+    let mut session = session::Session::with_clock(clock.clone()).with_rules(config.rules());
+    let mut recorder: Option<journal::Recorder> = None;
+    let mut forensics = forensics::Forensics::default();
+    let mut run_started = std::time::SystemTime::now();
+    let mut history = match history::History::default_path() {
+        Some(p) => match history::History::load(&p) {
+            Ok(h) => Some(h),
             Err(e) => {
-                report_error(CustomError { msg: e }, "Failed to load ROBOTO UI font");
+                log::warn!("history", "failed to load run history, runs won't be saved: {}", e);
                 None
             }
-        }
-        .unwrap();
-
-    let intro_text = match ROBOTO_font
-        .render("Click any keys or left/right click on your mouse to start tracking.")
-        .shaded(sdl2::pixels::Color::WHITE, sdl2::pixels::Color::BLACK)
-    {
-        Ok(t) => Some(t),
-        Err(e) => {
-            report_error(e, "Failed to render intro text");
+        },
+        None => {
+            log::warn!("history", "no data directory found, runs won't be saved");
             None
         }
-    }
-    .unwrap();
-
-    let tc = canvas.texture_creator();
+    };
 
-    let textured_intro = match tc.create_texture_from_surface(intro_text) {
-        Ok(t) => Some(t),
-        Err(e) => {
-            report_error(e, "Failed to convert intro text to texture");
-            None
-        }
-    }
-    .unwrap();
+    // fps stuff
+    let mut frame_stats = frame_stats::FrameStats::new(frame_stats::DEFAULT_CAPACITY);
+    let mut summary = None; // what the HUD shows, redone every HUD_REFRESH
+    let mut summarized = std::time::Instant::now();
+    let mut graph_mode = config.frame_graph;
 
-    // states
-    let mut clock_started = std::time::Instant::now();
-    let mut active = false;
-    let mut kb_active = false;
-    let mut mouse_button_active = false;
-    let mut mouse_move_active = false;
-    let mut mouse_wheel_active = false;
-    let mut ignore_first_keypress = true;
-    let mut failed_time = std::time::Instant::now();
-    let mut failed = false;
+    let mut benchmark = parsed.benchmark.map(benchmark::Benchmark::new);
 
-    // fps stuff
-    let mut ft = std::time::Instant::now(); // frame time
-    let mut fc = 0; // frame count
-    let mut fps = 0.0; // frame per sec
-    let mut mf = 0.0; // maximum fps
-    let mut lf = 0.0; // minimum fps (shows on screen)
-    let mut lpf = 0.0; // act as a cache
-    let mut lft = std::time::Instant::now(); // minimum frame refresh time thingy
-
-    let (width, height) = canvas.output_size().unwrap();
-    'running: while running {
-        // println!("Rendering");
-        for event in event_pump.poll_iter() {
-            if !active {
+    // made last so startup isn't counted as the first frame
+    let mut limiter = frame_limiter::FrameLimiter::new(config.fps_cap(), config.limiter);
+    match limiter.frame_duration() {
+        Some(frame) => log::info!(
+            "limiter",
+            "strategy={} fps={} frame={:.3}ms",
+            limiter.strategy().key(),
+            config.fps,
+            frame.as_secs_f64() * 1000.0
+        ),
+        None => log::info!("limiter", "uncapped"),
+    }
+    // SDL's first look at the events is the first frame's
+    if let Some(m) = idle.as_mut() {
+        m.sdl_resets_from(std::time::Instant::now());
+    }
+    // every way out, an error included, goes past the same save below
+    let outcome = (|| -> Result<(), error::Error> {
+        'running: loop {
+            if shutdown.load(std::sync::atomic::Ordering::SeqCst) {
+                log::info!("main", "shutdown requested by a signal");
+                break 'running;
+            }
+            if benchmark.as_ref().is_some_and(|b| b.is_done()) {
+                break 'running;
+            }
+            // saved as soon as it's over, whatever happens to the result screen
+            let mut failed = false;
+            let mut events = Vec::new();
+            let polled = std::time::Instant::now();
+            pause_chord.expire(std::time::Instant::now(), &mut events);
+            for event in event_pump.poll_iter() {
+                if from_device(&event) {
+                    input_seen = std::time::Instant::now();
+                }
+                let is_input = input_from_event(&event, controllers.as_ref()).is_some();
+                pause_chord.feed(std::time::Instant::now(), event, is_input, &mut events);
+            }
+            // held back modifier presses can be older than what the session has already seen, each event is
+            // handled at its own time and the clock carries on from the latest one after
+            let mut latest = session::Clock::now(&clock);
+            for filtered in events {
+                let at = filtered.at();
+                latest = latest.max(at);
+                clock.set(at);
+                let event = match filtered {
+                    chord::Filtered::Event(_, event) => event,
+                    chord::Filtered::Chord(_) => {
+                        let (transition, entry) = match session.phase() {
+                            session::Phase::Paused => (session.resume(), journal::Entry::Resume),
+                            _ => (session.pause(), journal::Entry::Pause),
+                        };
+                        if let Some(t) = transition {
+                            log_transition(t, &session);
+                            if let Some(r) = recorder.as_mut() {
+                                r.record(at, entry);
+                            }
+                        }
+                        continue;
+                    }
+                };
                 match event {
                     sdl2::event::Event::Quit { .. }
                     | sdl2::event::Event::KeyDown {
                         keycode: Some(sdl2::keyboard::Keycode::Escape),
                         ..
                     } => {
-                        println!("Quit");
+                        log::info!("main", "quit");
                         break 'running;
                     }
-                    sdl2::event::Event::KeyDown { .. }
-                    | sdl2::event::Event::MouseButtonDown { .. } => {
-                        active = true;
-                        kb_active = false;
-                        mouse_button_active = false;
-                        mouse_move_active = false;
-                        mouse_wheel_active = false;
-                        failed = false;
-                        println!("Activated");
-                        clock_started = Instant::now();
-                    }
-                    _ => {}
-                }
-            } else {
-                match event {
-                    sdl2::event::Event::Quit { .. }
-                    | sdl2::event::Event::KeyDown {
-                        keycode: Some(sdl2::keyboard::Keycode::Escape),
+                    sdl2::event::Event::Window {
+                        win_event: sdl2::event::WindowEvent::SizeChanged(..),
                         ..
                     } => {
-                        println!("Quit");
-                        break 'running;
+                        (width, height) = canvas.output_size().map_err(|e| error::Error::Render("the window", e))?;
                     }
-                    sdl2::event::Event::KeyDown { .. } => { //  | sdl2::event::Event::KeyUp { .. }
-                        if ignore_first_keypress {
-                            ignore_first_keypress = false;
-                            continue;
-                        }
-                        kb_active = true;
-                        if !failed {
-                            failed_time = std::time::Instant::now();
-                            failed = true;
+                    sdl2::event::Event::KeyDown {
+                        keycode: Some(sdl2::keyboard::Keycode::F3),
+                        repeat,
+                        ..
+                    } => {
+                        // the overlay hotkey doesn't count as input
+                        if !repeat {
+                            graph_mode = graph_mode.next();
                         }
+                        continue;
                     }
-                    sdl2::event::Event::MouseMotion { .. } => {
-                        if ignore_first_keypress {
-                            ignore_first_keypress = false;
-                            continue;
-                        }
-                        mouse_move_active = true;       
-                        if !failed {
-                            failed_time = std::time::Instant::now();
-                            failed = true;
+                    _ => {}
+                }
+                if let Some(c) = controllers.as_mut() {
+                    c.handle(&event);
+                }
+                if let Some(detail) = focus_lost(&event) {
+                    let phase = session.phase();
+                    let transition = session.focus_lost();
+                    log::debug!(
+                        "focus",
+                        "{} phase={:?} result={}",
+                        detail,
+                        phase,
+                        transition.map_or("ignored".to_string(), |t| format!("{:?}", t))
+                    );
+                    forensics.observe_focus_lost(transition, session.elapsed(), detail.clone());
+                    failed |= transition == Some(session::Transition::Failed);
+                    if let Some(r) = recorder.as_mut() {
+                        r.record(at, journal::Entry::FocusLost);
+                        if transition == Some(session::Transition::Failed) {
+                            r.record(at, journal::Entry::Offender(detail));
                         }
                     }
-                    sdl2::event::Event::MouseButtonDown { .. } => { // | sdl2::event::Event::MouseButtonUp { .. }
-                        if ignore_first_keypress {
-                            ignore_first_keypress = false;
-                            continue;
-                        }
-                        mouse_button_active = true;
-                        if !failed {
-                            failed_time = std::time::Instant::now();
-                            failed = true;
-                        }
+                    if let Some(t) = transition {
+                        log_transition(t, &session);
+                    }
+                } else if focus_gained(&event) {
+                    let transition = session.focus_gained();
+                    if let Some(r) = recorder.as_mut() {
+                        r.record(at, journal::Entry::FocusGained);
                     }
-                    sdl2::event::Event::MouseWheel { .. } => {
-                        if ignore_first_keypress {
-                            ignore_first_keypress = false;
-                            continue;
+                    if let Some(t) = transition {
+                        log_transition(t, &session);
+                    }
+                }
+                if let Some(input) = input_from_event(&event, controllers.as_ref()) {
+                    let describe = || describe_event(&event);
+                    match feed_input(&mut session, &mut forensics, &mut recorder, config, (at, input), describe) {
+                        Some(session::Transition::CountdownStarted | session::Transition::Activated) => {
+                            run_started = std::time::SystemTime::now();
                         }
-                        mouse_wheel_active = true;
-                        if !failed {
-                            failed_time = std::time::Instant::now();
-                            failed = true;
+                        Some(session::Transition::Failed) => failed = true,
+                        _ => {}
+                    }
+                }
+            }
+            clock.set(latest);
+            if let Some(d) = devices.as_mut() {
+                let now = std::time::Instant::now().max(session::Clock::now(&clock));
+                activity.clear();
+                d.poll(now, &mut activity);
+                if !activity.is_empty() {
+                    input_seen = now;
+                }
+                // read either way, so what piled up while focused doesn't count once focus is gone
+                if !session.focused() {
+                    clock.set(now);
+                    for a in &activity {
+                        let describe = || a.describe();
+                        let fed = (now, a.input);
+                        match feed_input(&mut session, &mut forensics, &mut recorder, config, fed, describe) {
+                            Some(session::Transition::CountdownStarted | session::Transition::Activated) => {
+                                run_started = std::time::SystemTime::now();
+                            }
+                            Some(session::Transition::Failed) => failed = true,
+                            _ => {}
                         }
                     }
-                    _ => {}
                 }
             }
-        }
-        if !active {
-            match canvas.copy(
-                &textured_intro,
-                None,
-                Some(get_middle_texture(&textured_intro, &canvas, None)),
-            ) {
-                Err(e) => report_error(
-                    CustomError { msg: e },
-                    "Failed to copy intro text to canvas",
-                ),
+            let now = std::time::Instant::now();
+            clock.set(now);
+            if let Some(at) = idle.as_mut().and_then(|m| m.unseen(input_seen, polled)) {
+                if session.unseen_input(at) {
+                    log::warn!(
+                        "idle",
+                        "the OS saw input {:.3}s ago that never got here, the run is flagged",
+                        (now - at).as_secs_f64()
+                    );
+                    if let Some(r) = recorder.as_mut() {
+                        r.record(now, journal::Entry::UnseenInput);
+                    }
+                }
+            }
+            let transition = session.tick();
+            forensics.observe_tick(transition, session.elapsed());
+            if let Some(t) = transition {
+                log_transition(t, &session);
+            }
+            if transition == Some(session::Transition::Activated) {
+                run_started = std::time::SystemTime::now();
+            }
+            match (transition, recorder.as_mut()) {
+                // the journal ends on the result, replaying it should leave it on screen
+                (Some(session::Transition::Dismissed), Some(_)) => {
+                    save_journal(recorder.take().unwrap().finish(now));
+                }
+                (Some(_), Some(r)) => r.record(now, journal::Entry::Tick),
                 _ => {}
-            };
-        } else {
-            if kb_active || mouse_button_active || mouse_move_active || mouse_wheel_active {
-                failed = true;
-                // let mut duration_time = "";
-                let mut reasoning: Vec<&str> = Vec::new();
-                if kb_active {
-                    reasoning.push("Keyboard presses detected");
-                } 
-                if mouse_button_active {
-                    reasoning.push("Mouse button presses detected")
+            }
+            if failed || transition == Some(session::Transition::Failed) {
+                save_run(history.as_mut(), run_record(&session, run_started, config, false));
+            }
+            match session.phase() {
+                session::Phase::Intro | session::Phase::Cooldown => {
+                    let intro_error = |e| error::Error::Render("the intro text", e);
+                    ui_text.layout(&mut intro_label, INTRO_TEXT, Some(width)).map_err(intro_error)?;
+                    ui_text
+                        .layout(&mut best_label, &best_text(history.as_ref()), Some(width))
+                        .map_err(intro_error)?;
+                    let y = (height as i32 - ui_text.height(&intro_label)) / 2;
+                    let y = ui_text
+                        .draw(&mut canvas, &intro_label, text::Align::Center(width), y)
+                        .map_err(intro_error)?;
+                    ui_text
+                        .draw(&mut canvas, &best_label, text::Align::Center(width), y)
+                        .map_err(intro_error)?;
                 }
-                if mouse_move_active {
-                    reasoning.push("Mouse movement detected")
+                session::Phase::Countdown | session::Phase::Resuming => {
+                    let left = session.countdown_left().unwrap_or_default();
+                    countdown::draw(
+                        &mut canvas,
+                        &mut countdown_text,
+                        &mut countdown_label,
+                        config.countdown_style,
+                        (left, config.countdown),
+                        config.foreground,
+                        (width, height),
+                    )
+                    .map_err(|e| error::Error::Render("the countdown", e))?;
                 }
-                if mouse_wheel_active {
-                    reasoning.push("Mouse wheel movement detected")
+                session::Phase::Failed => {
+                    let text = status_text(&session);
+                    let mut y = draw_status(&mut ui_text, &mut canvas, &mut status_label, &text, (width, height))?;
+                    for (label, line) in forensics_labels.iter_mut().zip(forensics.lines()) {
+                        let forensics_error = |e| error::Error::Render("the first offending event", e);
+                        hud_text.layout(label, &line, Some(width)).map_err(forensics_error)?;
+                        y = hud_text
+                            .draw(&mut canvas, label, text::Align::Center(width), y)
+                            .map_err(forensics_error)?;
+                    }
                 }
-                let duration_time = format!(
-                    "Inactive for {}",
-                    format_duration(failed_time - clock_started)
-                );
-                let wrapped = word_wrap(
-                    &format!("{} Reason: {}", duration_time, reasoning.join(", ") + "."), width, &ROBOTO_font
-                );
-                let h = wrapped.len() as i32 * ROBOTO_font.height();
-                let mut yc = (((height / 2) - h as u32) / 2) as i32;
-                for line in wrapped {
-                    let rendered = ROBOTO_font.render(&line).shaded(sdl2::pixels::Color::WHITE, sdl2::pixels::Color::BLACK).unwrap();
-                    canvas.copy(
-                        &tc.create_texture_from_surface(&rendered).unwrap(),
-                        None,
-                        sdl2::rect::Rect::new(
-                            ((width - rendered.width()) / 2) as i32,
-                            yc as i32,
-                            rendered.width(),
-                            rendered.height()
-                        )
-                    ).unwrap();
-                    yc += ROBOTO_font.height();
+                session::Phase::Paused => {
+                    let text = status_text(&session);
+                    let y = draw_status(&mut ui_text, &mut canvas, &mut status_label, &text, (width, height))?;
+                    if let Some(chord) = &config.pause_chord {
+                        let hint_error = |e| error::Error::Render("the pause hint", e);
+                        hud_text
+                            .layout(&mut pause_hint_label, &format!("Press {} to resume", chord.key()), Some(width))
+                            .map_err(hint_error)?;
+                        hud_text
+                            .draw(&mut canvas, &pause_hint_label, text::Align::Center(width), y)
+                            .map_err(hint_error)?;
+                    }
                 }
-                if (std::time::Instant::now() - failed_time).as_secs() >= 5 {
-                    active = false;
+                session::Phase::Armed | session::Phase::Tracking => {
+                    let text = status_text(&session);
+                    draw_status(&mut ui_text, &mut canvas, &mut status_label, &text, (width, height))?;
                 }
-            } else { // wow
-                let wrapped = word_wrap(
-                    &format!("Have been inactive for {}. Keep going!", format_duration(std::time::Instant::now() - clock_started)), width, &ROBOTO_font
-                );
-                let h = wrapped.len() as i32 * ROBOTO_font.height();
-                let mut yc = (((height / 2) - h as u32) / 2) as i32;
-                for line in wrapped {
-                    let rendered = ROBOTO_font.render(&line).shaded(sdl2::pixels::Color::WHITE, sdl2::pixels::Color::BLACK).unwrap();
-                    canvas.copy(
-                        &tc.create_texture_from_surface(&rendered).unwrap(),
-                        None,
-                        sdl2::rect::Rect::new(
-                            ((width - rendered.width()) / 2) as i32,
-                            yc as i32,
-                            rendered.width(),
-                            rendered.height()
-                        )
-                    ).unwrap();
-                    yc += ROBOTO_font.height();
+            }
+            if summarized.elapsed() >= HUD_REFRESH {
+                summary = frame_stats.summary(Some(config.stats_window));
+                summarized = std::time::Instant::now();
+            }
+            let [fps_line, range_line, times_line] = hud_stats(summary.as_ref());
+            let hud = [
+                fps_line,
+                range_line,
+                times_line,
+                match config.fps_cap() {
+                    Some(cap) => format!(
+                        "Capped FPS: {} ({}, {} ms late)",
+                        cap,
+                        limiter.strategy().key(),
+                        truncate(late.as_secs_f64() * 1000.0, 2)
+                    ),
+                    None => "Capped FPS: Unlimited".to_string(),
+                },
+                renderer_line.clone(),
+            ];
+            let mut hud_y = 0;
+            for (label, line) in hud_labels.iter_mut().zip(hud) {
+                let hud_error = |e| error::Error::Render("the HUD", e);
+                hud_text.layout(label, &line, None).map_err(hud_error)?;
+                hud_y = hud_text
+                    .draw(&mut canvas, label, text::Align::Left(0), hud_y)
+                    .map_err(hud_error)?;
+            }
+            if let Some(area) = graph_mode.area(hud_y + 4, width) {
+                if let Err(e) = frame_graph::draw(&mut canvas, &frame_stats, limiter.frame_duration(), area) {
+                    log::warn!("render", "failed to draw the frame graph: {}", e);
                 }
             }
+            canvas.present();
+            canvas.clear();
+            let timing = limiter.wait();
+            log::trace!(
+                "frame",
+                "frame_time={:.3}ms late={:.3}ms",
+                timing.frame_time.as_secs_f64() * 1000.0,
+                timing.late.as_secs_f64() * 1000.0
+            );
+            if timing.resynced {
+                log::debug!("limiter", "fell more than a frame behind, restarting the schedule");
+            }
+            frame_stats.push(timing.frame_time);
+            if let Some(b) = benchmark.as_mut() {
+                b.push(timing.frame_time);
+            }
+            late = timing.late;
+        }
+        Ok(())
+    })();
+
+    let now = std::time::Instant::now();
+    clock.set(now);
+    match session.phase() {
+        session::Phase::Armed | session::Phase::Tracking | session::Phase::Paused | session::Phase::Resuming => {
+            log::info!("history", "saving the run that was still going as interrupted");
+            save_run(history.as_mut(), run_record(&session, run_started, config, true));
+        }
+        // saved when it failed
+        session::Phase::Failed | session::Phase::Intro | session::Phase::Countdown | session::Phase::Cooldown => {}
+    }
+    if let Some(r) = recorder {
+        save_journal(r.finish(now));
+    }
+    outcome?;
+
+    if let Some(b) = &benchmark {
+        let output_size = canvas.output_size().unwrap_or((width, height));
+        let (json, csv) = b
+            .write(&parsed.benchmark_report, &canvas.info(), output_size, config)
+            .map_err(|e| error::Error::Io("Failed to write the benchmark report".to_string(), e))?;
+        println!("Wrote {} and {}", json.display(), csv.display());
+    }
+    Ok(())
+}
+
+/// Set by Ctrl-C, SIGTERM and SIGHUP, whatever loop is running checks it.
+fn shutdown_flag() -> std::sync::Arc<std::sync::atomic::AtomicBool> {
+    let shutdown = std::sync::Arc::new(std::sync::atomic::AtomicBool::new(false));
+    let flag = shutdown.clone();
+    if let Err(e) = ctrlc::set_handler(move || flag.store(true, std::sync::atomic::Ordering::SeqCst)) {
+        log::warn!("main", "couldn't install the signal handler, signals will kill the game outright: {}", e);
+    }
+    shutdown
+}
+
+/// Hands `input` to the session at `now`, keeping the journal and the result screen up to date.
+fn feed_input(
+    session: &mut session::Session<session::ManualClock>,
+    forensics: &mut forensics::Forensics,
+    recorder: &mut Option<journal::Recorder>,
+    config: &config::Config,
+    (now, input): (std::time::Instant, session::Input),
+    describe: impl FnOnce() -> String,
+) -> Option<session::Transition> {
+    let phase = session.phase();
+    let transition = session.handle(input);
+    let detail = (transition == Some(session::Transition::Failed)).then(describe);
+    match (transition, recorder.as_mut()) {
+        // the key that put the result away isn't part of the run
+        (Some(session::Transition::Dismissed), Some(_)) => {
+            save_journal(recorder.take().unwrap().finish(now));
         }
-        let fps_text = fps_font
-            .render(&format!("FPS: {}", truncate(fps, 2)))
-            .shaded(sdl2::pixels::Color::WHITE, sdl2::pixels::Color::BLACK)
-            .unwrap();
-        let mf_text = fps_font
-            .render(&format!("Maximum FPS: {}", truncate(mf, 2)))
-            .shaded(sdl2::pixels::Color::WHITE, sdl2::pixels::Color::BLACK)
-            .unwrap();
-        let lfp_text = fps_font
-            .render(&format!("Minimum FPS: {}", truncate(lf, 2)))
-            .shaded(sdl2::pixels::Color::WHITE, sdl2::pixels::Color::BLACK)
-            .unwrap();
-        let fc_text = fps_font
-            .render(&format!("Capped FPS: {}", fl))
-            .shaded(sdl2::pixels::Color::WHITE, sdl2::pixels::Color::BLACK)
-            .unwrap();
-        canvas
-            .copy(
-                &tc.create_texture_from_surface(&fps_text).unwrap(),
-                None,
-                Some(sdl2::rect::Rect::new(
-                    0,
-                    0,
-                    fps_text.width(),
-                    fps_text.height(),
-                )),
-            )
-            .unwrap();
-        canvas
-            .copy(
-                &tc.create_texture_from_surface(&mf_text).unwrap(),
-                None,
-                Some(sdl2::rect::Rect::new(
-                    0,
-                    20,
-                    mf_text.width(),
-                    mf_text.height(),
-                )),
-            )
-            .unwrap();
-        canvas
-            .copy(
-                &tc.create_texture_from_surface(&lfp_text).unwrap(),
-                None,
-                Some(sdl2::rect::Rect::new(
-                    0,
-                    40,
-                    lfp_text.width(),
-                    lfp_text.height(),
-                )),
-            )
-            .unwrap();
-        canvas
-            .copy(
-                &tc.create_texture_from_surface(&fc_text).unwrap(),
-                None,
-                Some(sdl2::rect::Rect::new(
-                    0,
-                    60,
-                    fc_text.width(),
-                    fc_text.height(),
-                )),
-            )
-            .unwrap();
-        canvas.present();
-        fc += 1;
-        let elapsed_time = ft.elapsed();
-        if elapsed_time.as_secs() >= 1 {
-            fps = fc as f64 / elapsed_time.as_secs_f64();
-            fc = 0;
-            ft = std::time::Instant::now();
-            if fps > mf {
-                mf = fps
-            } else if fps < lpf {
-                lpf = fps
+        (_, Some(r)) => {
+            r.record(now, journal::Entry::Input(input));
+            if let Some(detail) = &detail {
+                r.record(now, journal::Entry::Offender(detail.clone()));
             }
         }
-        let elapsed_time = lft.elapsed();
-        if elapsed_time.as_secs() >= 3 {
-            lf = lpf;
-            lpf = fps;
-            lft = std::time::Instant::now();
+        _ => {}
+    }
+    forensics.observe(input, transition, session.elapsed(), detail);
+    log::debug!(
+        "input",
+        "{} {:?} phase={:?} result={}",
+        session::Reason::from(input).key(),
+        input,
+        phase,
+        transition.map_or("ignored".to_string(), |t| format!("{:?}", t))
+    );
+    if let Some(t) = transition {
+        log_transition(t, session);
+    }
+    if matches!(
+        transition,
+        Some(session::Transition::CountdownStarted | session::Transition::Activated)
+    ) && config.record_journal
+    {
+        *recorder = Some(journal::Recorder::start(session, input, now));
+    }
+    transition
+}
+
+/// Swallowed input is only logged at debug level along with every other input.
+fn log_transition<C: session::Clock>(transition: session::Transition, session: &session::Session<C>) {
+    match transition {
+        session::Transition::Swallowed | session::Transition::Tallied => {}
+        session::Transition::Failed => log::info!(
+            "session",
+            "{:?} phase={:?} inactive={:.3}s reasons={}",
+            transition,
+            session.phase(),
+            session.elapsed().as_secs_f64(),
+            session.reasons().iter().map(|r| r.key()).collect::<Vec<_>>().join(",")
+        ),
+        _ => log::info!("session", "{:?} phase={:?}", transition, session.phase()),
+    }
+}
+
+/// What the screen says about the run, the same live and in a replay.
+fn status_text<C: session::Clock>(session: &session::Session<C>) -> String {
+    match session.phase() {
+        session::Phase::Intro | session::Phase::Cooldown => INTRO_TEXT.to_string(),
+        session::Phase::Countdown | session::Phase::Resuming => {
+            countdown::digit(session.countdown_left().unwrap_or_default()).to_string()
+        }
+        session::Phase::Paused => format!("Paused. Inactive for {} so far.", format_duration(session.elapsed())),
+        session::Phase::Failed => format!(
+            "Inactive for {} Reason: {}.{}",
+            format_duration(session.elapsed()),
+            session.reasons().descriptions().join(", "),
+            if session.awaiting_key() { " Press any key to continue." } else { "" }
+        ),
+        session::Phase::Armed | session::Phase::Tracking => {
+            format!("Have been inactive for {}. Keep going!", format_duration(session.elapsed()))
+        }
+    }
+}
+
+fn run_record<C: session::Clock>(
+    session: &session::Session<C>,
+    started: std::time::SystemTime,
+    config: &config::Config,
+    interrupted: bool,
+) -> history::Record {
+    history::Record {
+        started,
+        inactive: session.elapsed(),
+        reasons: session.reasons(),
+        fps_cap: config.fps_cap().map(u64::from),
+        interrupted,
+        background: session.background(),
+        idle_mismatch: session.idle_mismatch(),
+        paused: session.paused(),
+    }
+}
+
+fn save_run(history: Option<&mut history::History>, record: history::Record) {
+    if let Some(h) = history {
+        if let Err(e) = h.push(record) {
+            log::error!("history", "failed to save run: {}", e);
         }
-        canvas.clear();
-        capper.limit_fps();
+    }
+}
+
+fn save_journal(journal: journal::Journal) {
+    match journal::Journal::default_dir() {
+        Some(dir) => match journal.save(&dir) {
+            Ok(path) => log::info!("journal", "saved {}", path.display()),
+            Err(e) => log::error!("journal", "failed to save the journal: {}", e),
+        },
+        None => log::warn!("journal", "no data directory found, the journal wasn't saved"),
+    }
+}
+
+/// The status line of a run, centred in the top half of the window. Returns the y below it.
+fn draw_status(
+    atlas: &mut text::GlyphAtlas,
+    canvas: &mut sdl2::render::WindowCanvas,
+    label: &mut text::Label,
+    status: &str,
+    (width, height): (u32, u32),
+) -> Result<i32, error::Error> {
+    let status_error = |e| error::Error::Render("the status text", e);
+    atlas.layout(label, status, Some(width)).map_err(status_error)?;
+    let y = (height as i32 / 2 - atlas.height(label)) / 2;
+    atlas
+        .draw(canvas, label, text::Align::Center(width), y)
+        .map_err(status_error)
+}
+
+/// How often the HUD statistics get recomputed.
+const HUD_REFRESH: std::time::Duration = std::time::Duration::from_millis(500);
+
+const INTRO_TEXT: &str = "Click any keys or left/right click on your mouse to start tracking.";
+
+fn best_text(history: Option<&history::History>) -> String {
+    match history.and_then(|h| h.best()) {
+        Some(best) => format!("Personal best: {}", format_duration(best.inactive)),
+        None => String::new(),
+    }
+}
+
+fn input_from_event(
+    event: &sdl2::event::Event,
+    controllers: Option<&controllers::Controllers>,
+) -> Option<session::Input> {
+    match event {
+        sdl2::event::Event::KeyDown { .. } => Some(session::Input::KeyDown), //  | sdl2::event::Event::KeyUp { .. }
+        sdl2::event::Event::MouseButtonDown { .. } => Some(session::Input::MouseButtonDown), // | sdl2::event::Event::MouseButtonUp { .. }
+        sdl2::event::Event::MouseMotion { xrel, yrel, .. } => Some(session::Input::MouseMotion {
+            dx: *xrel,
+            dy: *yrel,
+        }),
+        sdl2::event::Event::MouseWheel { x, y, .. } => Some(session::Input::MouseWheel { x: *x, y: *y }),
+        sdl2::event::Event::ControllerButtonDown { .. }
+        | sdl2::event::Event::JoyButtonDown { .. }
+        | sdl2::event::Event::JoyHatMotion { .. }
+        | sdl2::event::Event::ControllerAxisMotion { .. }
+        | sdl2::event::Event::JoyAxisMotion { .. } => controllers?.input(event),
+        sdl2::event::Event::FingerDown { .. }
+        | sdl2::event::Event::FingerMotion { .. }
+        | sdl2::event::Event::ControllerTouchpadDown { .. }
+        | sdl2::event::Event::ControllerTouchpadMotion { .. } => Some(session::Input::Touch),
+        sdl2::event::Event::JoyDeviceAdded { .. }
+        | sdl2::event::Event::JoyDeviceRemoved { .. }
+        | sdl2::event::Event::ControllerDeviceRemapped { .. } => Some(session::Input::DeviceChanged),
+        _ => None,
+    }
+}
+
+/// Whether a device sent `event`, releases, hotkeys and input that doesn't count included.
+fn from_device(event: &sdl2::event::Event) -> bool {
+    event.is_keyboard() || event.is_mouse() || event.is_finger() || event.is_controller() || event.is_joy()
+}
+
+/// Why the game can't be seen or played anymore, if `event` says so.
+fn focus_lost(event: &sdl2::event::Event) -> Option<String> {
+    use sdl2::event::{DisplayEvent, Event, WindowEvent};
+    match event {
+        Event::Window { win_event, .. } => match win_event {
+            WindowEvent::FocusLost => Some("window lost focus".to_string()),
+            WindowEvent::Minimized => Some("window minimised".to_string()),
+            WindowEvent::Hidden => Some("window hidden".to_string()),
+            _ => None,
+        },
+        Event::Display {
+            display_index,
+            display_event: DisplayEvent::Disconnected,
+            ..
+        } => Some(format!("display {} disconnected", display_index + 1)),
+        _ => None,
+    }
+}
+
+fn focus_gained(event: &sdl2::event::Event) -> bool {
+    use sdl2::event::{Event, WindowEvent};
+    matches!(
+        event,
+        Event::Window {
+            win_event: WindowEvent::FocusGained | WindowEvent::Restored | WindowEvent::Shown,
+            ..
+        }
+    )
+}
+
+/// What the result screen says about the event that failed a run.
+fn describe_event(event: &sdl2::event::Event) -> String {
+    use sdl2::event::Event;
+    match event {
+        Event::KeyDown { keycode, scancode, .. } => format!(
+            "key {} (scancode {})",
+            keycode.map_or("unknown".to_string(), |k| k.name()),
+            scancode.map_or("unknown".to_string(), |s| format!("{} {}", s.name(), s as i32))
+        ),
+        Event::MouseButtonDown { mouse_btn, x, y, .. } => {
+            format!("mouse button {:?} at {}, {}", mouse_btn, x, y).to_lowercase()
+        }
+        Event::MouseMotion { xrel, yrel, x, y, .. } => {
+            format!("mouse moved by {}, {} to {}, {}", xrel, yrel, x, y)
+        }
+        Event::MouseWheel { x, y, .. } => {
+            format!("mouse wheel {} ({}, {})", forensics::wheel_direction(*x, *y), x, y)
+        }
+        Event::ControllerButtonDown { which, button, .. } => {
+            format!("controller {} button {}", which, button.string())
+        }
+        Event::JoyButtonDown { which, button_idx, .. } => format!("joystick {} button {}", which, button_idx),
+        Event::JoyHatMotion { which, hat_idx, state, .. } => {
+            format!("joystick {} hat {} {:?}", which, hat_idx, state).to_lowercase()
+        }
+        Event::ControllerAxisMotion { which, axis, value, .. } => {
+            format!("controller {} axis {} at {}", which, axis.string(), value)
+        }
+        Event::JoyAxisMotion { which, axis_idx, value, .. } => {
+            format!("joystick {} axis {} at {}", which, axis_idx, value)
+        }
+        Event::FingerDown { x, y, .. } | Event::FingerMotion { x, y, .. } => {
+            format!("touch at {:.2}, {:.2}", x, y)
+        }
+        Event::ControllerTouchpadDown { which, x, y, .. } | Event::ControllerTouchpadMotion { which, x, y, .. } => {
+            format!("controller {} touchpad at {:.2}, {:.2}", which, x, y)
+        }
+        Event::JoyDeviceAdded { which, .. } => format!("joystick {} connected", which),
+        Event::JoyDeviceRemoved { which, .. } => format!("joystick {} disconnected", which),
+        Event::ControllerDeviceRemapped { which, .. } => format!("controller {} remapped", which),
+        _ => "unknown event".to_string(),
     }
 }
 
@@ -529,36 +1185,6 @@ fn _get_middle_surface(
     return r;
 }
 
-fn get_middle_texture(
-    surface: &sdl2::render::Texture,
-    window: &sdl2::render::Canvas<sdl2::video::Window>,
-    y: Option<u32>,
-) -> sdl2::rect::Rect {
-    let (w, h) = window.output_size().unwrap();
-    let r: sdl2::rect::Rect;
-
-    match y {
-        Some(pos) => {
-            r = sdl2::rect::Rect::new(
-                ((w - surface.query().width) / 2) as i32,
-                ((pos as u32 - surface.query().height) / 2) as i32,
-                surface.query().width,
-                surface.query().height,
-            );
-        }
-        None => {
-            r = sdl2::rect::Rect::new(
-                ((w - surface.query().width) / 2) as i32,
-                ((h - surface.query().height) / 2) as i32,
-                surface.query().width,
-                surface.query().height,
-            );
-        }
-    }
-
-    return r;
-}
-
 fn format_duration(dur: std::time::Duration) -> String {
     let seconds = dur.as_secs();
     let minutes = seconds / 60;
@@ -621,33 +1247,36 @@ fn format_duration(dur: std::time::Duration) -> String {
 
     formatted_duration
 }
-fn word_wrap<'a, 'b, 'c>(
-    text: &str,
-    max_width: u32,
-    font: &'c sdl2::ttf::Font<'a, 'b>,
-) -> Vec<String> {
-    let words = text.split_whitespace();
-    let mut lines: Vec<String> = Vec::new();
-    let mut current_line = String::new();
-
-    for word in words {
-        let test_line = current_line.clone() + word + " ";
-        let (test_width, _) = font.size_of(&test_line).unwrap();
-
-        if test_width <= max_width {
-            current_line = test_line;
-        } else {
-            lines.push(current_line.trim_end().to_string());
-            current_line = word.to_owned() + " ";
+/// The three statistics lines of the HUD.
+fn hud_stats(summary: Option<&frame_stats::Summary>) -> [String; 3] {
+    let s = match summary {
+        Some(s) => s,
+        None => {
+            return [
+                "FPS: -".to_string(),
+                "Maximum FPS: -, Minimum FPS: -".to_string(),
+                "Frame time: -".to_string(),
+            ]
         }
-    }
-
-    if !current_line.trim().is_empty() {
-        lines.push(current_line.trim_end().to_string());
-    }
-
-    // println!("{:#?}", lines);
-    lines
+    };
+    let ms = |d: std::time::Duration| truncate(d.as_secs_f64() * 1000.0, 2);
+    let fps = |d: std::time::Duration| truncate(1.0 / d.as_secs_f64().max(f64::EPSILON), 2);
+    [
+        format!(
+            "FPS: {} (1% low: {}, 0.1% low: {})",
+            truncate(s.avg_fps, 2),
+            truncate(s.low_1_fps, 2),
+            truncate(s.low_01_fps, 2)
+        ),
+        format!("Maximum FPS: {}, Minimum FPS: {}", fps(s.min), fps(s.max)),
+        format!(
+            "Frame time: p50 {} ms, p95 {} ms, p99 {} ms, std dev {} ms",
+            ms(s.p50),
+            ms(s.p95),
+            ms(s.p99),
+            ms(s.std_dev)
+        ),
+    ]
 }
 
 fn truncate(b: f64, precision: usize) -> f64 {
@@ -0,0 +1,72 @@
+use sdl2::render::{CanvasBuilder, WindowCanvas};
+use sdl2::video::Window;
+
+use crate::log;
+
+enum Attempt {
+    /// 1 based, as listed by `--list-gpu-renderers`.
+    Requested(usize),
+    Accelerated,
+    Software,
+}
+
+impl Attempt {
+    fn describe(&self) -> String {
+        match self {
+            Attempt::Requested(i) => format!("renderer #{}", i),
+            Attempt::Accelerated => "an accelerated renderer".to_string(),
+            Attempt::Software => "the software renderer".to_string(),
+        }
+    }
+}
+
+/// Tries the requested renderer, then any accelerated one, then the software one, and
+/// prints why it had to move on each time. Only fails when none of them work.
+pub fn create_canvas(window: Window, requested: Option<usize>, vsync: bool) -> Result<WindowCanvas, String> {
+    let mut chain = Vec::with_capacity(3);
+    if let Some(i) = requested {
+        chain.push(Attempt::Requested(i));
+    }
+    chain.push(Attempt::Accelerated);
+    chain.push(Attempt::Software);
+
+    let drivers = sdl2::render::drivers().count();
+    let mut failures = Vec::new();
+    for attempt in chain {
+        // a failed build drops the window it was given, so every try gets its own handle
+        // and the window only goes away with the last one
+        // SAFETY: the handle shares the window's reference counted context, which is what destroys the SDL
+        // window. `window` keeps one alive for every try, and the canvas that gets returned keeps its own,
+        // so the window can't be destroyed while anything built here still uses it.
+        let builder = unsafe { Window::from_ref(window.context()) }.into_canvas();
+        let builder = if vsync { builder.present_vsync() } else { builder };
+        let result = match attempt {
+            Attempt::Requested(i) if i == 0 || i > drivers => {
+                Err(format!("there is no renderer #{}, see --list-gpu-renderers", i))
+            }
+            Attempt::Requested(i) => build(builder.index(i as u32 - 1)),
+            Attempt::Accelerated => build(builder.accelerated()),
+            Attempt::Software => build(builder.software()),
+        };
+        match result {
+            Ok(canvas) => {
+                let info = canvas.info();
+                if failures.is_empty() {
+                    log::info!("renderer", "using {} name={} flags={:#x}", attempt.describe(), info.name, info.flags);
+                } else {
+                    log::warn!("renderer", "falling back to {} name={} flags={:#x}", attempt.describe(), info.name, info.flags);
+                }
+                return Ok(canvas);
+            }
+            Err(e) => {
+                log::warn!("renderer", "couldn't use {}: {}", attempt.describe(), e);
+                failures.push(format!("{}: {}", attempt.describe(), e));
+            }
+        }
+    }
+    Err(format!("No renderer could be created ({})", failures.join("; ")))
+}
+
+fn build(builder: CanvasBuilder) -> Result<WindowCanvas, String> {
+    builder.build().map_err(|e| e.to_string())
+}
@@ -0,0 +1,183 @@
+use crate::session::{Input, Reason, Reasons};
+
+pub const DEFAULT_AXIS_THRESHOLD: u16 = 8000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Sensitivity {
+    /// Pixels the mouse has to travel (summed over the run) before it counts as movement. 0 counts everything.
+    pub motion_dead_zone: u32,
+    /// Smallest single wheel step that counts. 0 counts everything.
+    pub min_wheel_delta: u32,
+    /// How far a stick or trigger has to move from rest (out of 32767) before it counts.
+    pub axis_threshold: u16,
+    /// Devices whose input never fails a run.
+    pub ignored: Reasons,
+}
+
+impl Default for Sensitivity {
+    fn default() -> Self {
+        Sensitivity {
+            motion_dead_zone: 0,
+            min_wheel_delta: 0,
+            axis_threshold: DEFAULT_AXIS_THRESHOLD,
+            ignored: Reasons::default(),
+        }
+    }
+}
+
+/// Drops input that shouldn't count under the given [`Sensitivity`].
+#[derive(Debug, Clone, Default)]
+pub struct InputFilter {
+    sensitivity: Sensitivity,
+    motion: (i64, i64),
+}
+
+impl InputFilter {
+    pub fn new(sensitivity: Sensitivity) -> Self {
+        InputFilter {
+            sensitivity,
+            motion: (0, 0),
+        }
+    }
+
+    /// Forgets the motion accumulated so far, call when a new run starts.
+    pub fn reset(&mut self) {
+        self.motion = (0, 0);
+    }
+
+    pub fn accepts(&mut self, input: Input) -> bool {
+        if self.sensitivity.ignored.contains(Reason::from(input)) {
+            return false;
+        }
+        match input {
+            Input::MouseMotion { dx, dy } => {
+                self.motion.0 += dx as i64;
+                self.motion.1 += dy as i64;
+                let dead_zone = self.sensitivity.motion_dead_zone as i64;
+                let (x, y) = self.motion;
+                dead_zone == 0 || x * x + y * y > dead_zone * dead_zone
+            }
+            Input::MouseWheel { x, y } => {
+                x.unsigned_abs().max(y.unsigned_abs()) >= self.sensitivity.min_wheel_delta
+            }
+            Input::ControllerAxis { value } => value.unsigned_abs() > self.sensitivity.axis_threshold,
+            Input::KeyDown
+            | Input::MouseButtonDown
+            | Input::ControllerButtonDown
+            | Input::Touch
+            | Input::DeviceChanged => true,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn motion(dx: i32, dy: i32) -> Input {
+        Input::MouseMotion { dx, dy }
+    }
+
+    #[test]
+    fn motion_adds_up_until_it_leaves_the_dead_zone() {
+        let mut filter = InputFilter::new(Sensitivity {
+            motion_dead_zone: 10,
+            ..Sensitivity::default()
+        });
+        assert!(!filter.accepts(motion(3, 4)));
+        assert!(!filter.accepts(motion(3, 4)));
+        // right on the edge is still inside
+        assert_eq!(filter.motion, (6, 8));
+        assert!(!filter.accepts(motion(0, 0)));
+        assert!(filter.accepts(motion(0, 1)));
+        // out stays out, every move after that counts
+        assert!(filter.accepts(motion(0, 1)));
+    }
+
+    #[test]
+    fn moving_back_doesnt_add_up() {
+        let mut filter = InputFilter::new(Sensitivity {
+            motion_dead_zone: 10,
+            ..Sensitivity::default()
+        });
+        for _ in 0..100 {
+            assert!(!filter.accepts(motion(9, 0)));
+            assert!(!filter.accepts(motion(-9, 0)));
+        }
+    }
+
+    #[test]
+    fn reset_starts_the_dead_zone_over() {
+        let mut filter = InputFilter::new(Sensitivity {
+            motion_dead_zone: 10,
+            ..Sensitivity::default()
+        });
+        assert!(filter.accepts(motion(-20, 0)));
+        filter.reset();
+        assert!(!filter.accepts(motion(-10, 0)));
+        // other input in between doesn't reset it
+        assert!(filter.accepts(Input::KeyDown));
+        assert!(filter.accepts(motion(-1, 0)));
+    }
+
+    #[test]
+    fn no_dead_zone_counts_every_move() {
+        let mut filter = InputFilter::new(Sensitivity::default());
+        assert!(filter.accepts(motion(1, 0)));
+        assert!(filter.accepts(motion(-1, 0)));
+    }
+
+    #[test]
+    fn wheel_steps() {
+        let mut filter = InputFilter::new(Sensitivity {
+            min_wheel_delta: 2,
+            ..Sensitivity::default()
+        });
+        assert!(!filter.accepts(Input::MouseWheel { x: 0, y: 1 }));
+        assert!(!filter.accepts(Input::MouseWheel { x: -1, y: 1 }));
+        assert!(filter.accepts(Input::MouseWheel { x: 0, y: 2 }));
+        assert!(filter.accepts(Input::MouseWheel { x: -2, y: 0 }));
+        // steps don't add up
+        assert!(!filter.accepts(Input::MouseWheel { x: 0, y: 1 }));
+        let mut filter = InputFilter::default();
+        assert!(filter.accepts(Input::MouseWheel { x: 0, y: 0 }));
+    }
+
+    #[test]
+    fn axis_threshold() {
+        let mut filter = InputFilter::new(Sensitivity::default());
+        let axis = |value| Input::ControllerAxis { value };
+        assert!(!filter.accepts(axis(0)));
+        assert!(!filter.accepts(axis(DEFAULT_AXIS_THRESHOLD as i16)));
+        assert!(!filter.accepts(axis(-(DEFAULT_AXIS_THRESHOLD as i16))));
+        assert!(filter.accepts(axis(DEFAULT_AXIS_THRESHOLD as i16 + 1)));
+        assert!(filter.accepts(axis(-(DEFAULT_AXIS_THRESHOLD as i16) - 1)));
+        assert!(filter.accepts(axis(i16::MIN)));
+        let mut filter = InputFilter::new(Sensitivity {
+            axis_threshold: 0,
+            ..Sensitivity::default()
+        });
+        assert!(!filter.accepts(axis(0)));
+        assert!(filter.accepts(axis(1)));
+    }
+
+    #[test]
+    fn ignored_devices_never_count() {
+        let mut ignored = Reasons::default();
+        ignored.insert(Reason::MouseMotion);
+        ignored.insert(Reason::Keyboard);
+        let mut filter = InputFilter::new(Sensitivity {
+            motion_dead_zone: 10,
+            ignored,
+            ..Sensitivity::default()
+        });
+        assert!(!filter.accepts(Input::KeyDown));
+        assert!(!filter.accepts(motion(500, 500)));
+        // and don't count towards the dead zone either
+        assert_eq!(filter.motion, (0, 0));
+        assert!(filter.accepts(Input::MouseButtonDown));
+        assert!(filter.accepts(Input::ControllerButtonDown));
+        assert!(filter.accepts(Input::Touch));
+        assert!(filter.accepts(Input::DeviceChanged));
+    }
+}
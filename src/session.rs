@@ -0,0 +1,863 @@
+use std::cell::Cell;
+use std::rc::Rc;
+use std::time::{Duration, Instant};
+
+use crate::sensitivity::{InputFilter, Sensitivity};
+
+/// Something the player did, already stripped of whatever front end produced it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Input {
+    KeyDown,
+    MouseButtonDown,
+    MouseMotion { dx: i32, dy: i32 },
+    MouseWheel { x: i32, y: i32 },
+    /// Gamepad or joystick button, including d-pad hats.
+    ControllerButtonDown,
+    /// Gamepad stick/trigger or joystick axis position, -32768 to 32767.
+    ControllerAxis { value: i16 },
+    /// Finger on a touchscreen or a controller's touchpad.
+    Touch,
+    /// Gamepad or joystick plugged in, removed or remapped.
+    DeviceChanged,
+}
+
+/// Why a run ended.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Reason {
+    Keyboard,
+    MouseButton,
+    MouseMotion,
+    MouseWheel,
+    ControllerButton,
+    ControllerAxis,
+    Touch,
+    DeviceHotplug,
+    /// The window went to the background, got minimised or hidden, or its display went away.
+    FocusLost,
+}
+
+impl Reason {
+    pub const ALL: [Reason; 9] = [
+        Reason::Keyboard,
+        Reason::MouseButton,
+        Reason::MouseMotion,
+        Reason::MouseWheel,
+        Reason::ControllerButton,
+        Reason::ControllerAxis,
+        Reason::Touch,
+        Reason::DeviceHotplug,
+        Reason::FocusLost,
+    ];
+
+    pub fn description(&self) -> &'static str {
+        match self {
+            Reason::Keyboard => "Keyboard presses detected",
+            Reason::MouseButton => "Mouse button presses detected",
+            Reason::MouseMotion => "Mouse movement detected",
+            Reason::MouseWheel => "Mouse wheel movement detected",
+            Reason::ControllerButton => "Controller button presses detected",
+            Reason::ControllerAxis => "Controller stick movement detected",
+            Reason::Touch => "Touch input detected",
+            Reason::DeviceHotplug => "Controller plugged in or removed",
+            Reason::FocusLost => "Window lost focus",
+        }
+    }
+
+    /// Stable name used when a reason gets written somewhere.
+    pub fn key(&self) -> &'static str {
+        match self {
+            Reason::Keyboard => "keyboard",
+            Reason::MouseButton => "mouse_button",
+            Reason::MouseMotion => "mouse_motion",
+            Reason::MouseWheel => "mouse_wheel",
+            Reason::ControllerButton => "controller_button",
+            Reason::ControllerAxis => "controller_axis",
+            Reason::Touch => "touch",
+            Reason::DeviceHotplug => "device_hotplug",
+            Reason::FocusLost => "focus_lost",
+        }
+    }
+
+    pub fn from_key(key: &str) -> Option<Reason> {
+        Reason::ALL.into_iter().find(|r| r.key() == key)
+    }
+
+    fn bit(&self) -> u32 {
+        1 << Reason::ALL.iter().position(|r| r == self).unwrap()
+    }
+}
+
+impl From<Input> for Reason {
+    fn from(input: Input) -> Self {
+        match input {
+            Input::KeyDown => Reason::Keyboard,
+            Input::MouseButtonDown => Reason::MouseButton,
+            Input::MouseMotion { .. } => Reason::MouseMotion,
+            Input::MouseWheel { .. } => Reason::MouseWheel,
+            Input::ControllerButtonDown => Reason::ControllerButton,
+            Input::ControllerAxis { .. } => Reason::ControllerAxis,
+            Input::Touch => Reason::Touch,
+            Input::DeviceChanged => Reason::DeviceHotplug,
+        }
+    }
+}
+
+/// Set of failure reasons, always iterated in the same order regardless of arrival.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Reasons(u32);
+
+impl Reasons {
+    pub fn insert(&mut self, reason: Reason) {
+        self.0 |= reason.bit();
+    }
+
+    pub fn contains(&self, reason: Reason) -> bool {
+        self.0 & reason.bit() != 0
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = Reason> + '_ {
+        Reason::ALL.into_iter().filter(|r| self.contains(*r))
+    }
+
+    pub fn descriptions(&self) -> Vec<&'static str> {
+        self.iter().map(|r| r.description()).collect()
+    }
+
+    pub fn bits(&self) -> u32 {
+        self.0
+    }
+
+    /// Unknown bits are dropped.
+    pub fn from_bits(bits: u32) -> Reasons {
+        let mut reasons = Reasons::default();
+        for reason in Reason::ALL {
+            if bits & reason.bit() != 0 {
+                reasons.insert(reason);
+            }
+        }
+        reasons
+    }
+}
+
+pub trait Clock {
+    fn now(&self) -> Instant;
+}
+
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> Instant {
+        Instant::now()
+    }
+}
+
+/// Only moves when told to. Clones share the same time, so whoever drives the
+/// session can set it while the session holds its own copy.
+#[derive(Debug, Clone)]
+pub struct ManualClock(Rc<Cell<Instant>>);
+
+impl ManualClock {
+    pub fn new(now: Instant) -> Self {
+        ManualClock(Rc::new(Cell::new(now)))
+    }
+
+    pub fn set(&self, now: Instant) {
+        self.0.set(now)
+    }
+}
+
+impl Clock for ManualClock {
+    fn now(&self) -> Instant {
+        self.0.get()
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Phase {
+    /// Intro screen, waiting for the player to start a run.
+    Intro,
+    /// Counting down to the run, so letting go of the starting press doesn't end it.
+    Countdown,
+    /// Run started, the grace period is running or the first input is yet to be swallowed.
+    Armed,
+    /// Run in progress.
+    Tracking,
+    /// Run on hold, the clock is stopped and input is ignored.
+    Paused,
+    /// Counting down back into the paused run.
+    Resuming,
+    /// Run over, result is on screen and late input still gets tallied.
+    Failed,
+    /// Result dismissed, new runs can't be started until the cooldown passes.
+    Cooldown,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Transition {
+    CountdownStarted,
+    CountdownRestarted,
+    Activated,
+    Swallowed,
+    /// Armed run went live.
+    Tracking,
+    Paused,
+    /// The countdown back into the run started.
+    Resuming,
+    Resumed,
+    Failed,
+    /// Input while the result is up that got past the filter, added to the reasons.
+    Tallied,
+    Dismissed,
+    Reset,
+}
+
+/// What input during the countdown does.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CountdownInput {
+    Ignore,
+    /// Starts the countdown over, so the run only starts once the player has let go.
+    Restart,
+}
+
+impl CountdownInput {
+    pub fn key(&self) -> &'static str {
+        match self {
+            CountdownInput::Ignore => "ignore",
+            CountdownInput::Restart => "restart",
+        }
+    }
+
+    pub fn from_key(key: &str) -> Option<CountdownInput> {
+        [CountdownInput::Ignore, CountdownInput::Restart]
+            .into_iter()
+            .find(|c| c.key() == key)
+    }
+}
+
+/// What happens to a run when the window loses focus.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FocusPolicy {
+    Fail,
+    /// Pauses until the window is back, then resumes through the countdown.
+    Pause,
+    /// Keeps going, but the run is marked as played in the background.
+    Ignore,
+}
+
+impl FocusPolicy {
+    pub const ALL: [FocusPolicy; 3] = [FocusPolicy::Fail, FocusPolicy::Pause, FocusPolicy::Ignore];
+
+    pub fn key(&self) -> &'static str {
+        match self {
+            FocusPolicy::Fail => "fail",
+            FocusPolicy::Pause => "pause",
+            FocusPolicy::Ignore => "ignore",
+        }
+    }
+
+    pub fn from_key(key: &str) -> Option<FocusPolicy> {
+        FocusPolicy::ALL.into_iter().find(|p| p.key() == key)
+    }
+}
+
+/// The knobs that decide what counts as activity and how long each part of a run lasts.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Rules {
+    pub sensitivity: Sensitivity,
+    /// Between the starting press and the run, zero starts it right away.
+    pub countdown: Duration,
+    pub countdown_input: CountdownInput,
+    /// Input right after starting a run is ignored for this long.
+    pub grace_period: Duration,
+    /// Swallow the first input of every run, usually the release of the starting press.
+    pub ignore_first_input: bool,
+    /// How long the result stays up before it can go away.
+    pub result_duration: Duration,
+    /// Keep the result up until a key is pressed instead of dismissing it on its own.
+    pub wait_for_key: bool,
+    pub focus: FocusPolicy,
+}
+
+impl Default for Rules {
+    fn default() -> Self {
+        Rules {
+            sensitivity: Sensitivity::default(),
+            countdown: Duration::from_secs(3),
+            countdown_input: CountdownInput::Ignore,
+            grace_period: Duration::ZERO,
+            ignore_first_input: true,
+            result_duration: Duration::from_secs(5),
+            wait_for_key: false,
+            focus: FocusPolicy::Fail,
+        }
+    }
+}
+
+pub struct Session<C: Clock = SystemClock> {
+    clock: C,
+    phase: Phase,
+    reasons: Reasons,
+    rules: Rules,
+    filter: InputFilter,
+    countdown_started: Instant,
+    clock_started: Instant,
+    failed_time: Instant,
+    cooldown_started: Instant,
+    pause_started: Instant,
+    /// What the run was doing before it got paused.
+    paused_from: Phase,
+    /// Time spent paused this run, not counting a pause that's still going.
+    paused: Duration,
+    /// The pause came from losing focus, so getting it back resumes.
+    paused_by_focus: bool,
+    /// Whether the window has focus, the [`FocusPolicy`] applies once a run is live without it.
+    focused: bool,
+    /// The window lost focus during the run and it kept going.
+    background: bool,
+    /// The OS saw input during the run that never got here.
+    idle_mismatch: bool,
+    /// Whether this run still has an input to swallow.
+    ignore_first_input: bool,
+    cooldown_duration: Duration,
+}
+
+impl Default for Session<SystemClock> {
+    fn default() -> Self {
+        Session::with_clock(SystemClock)
+    }
+}
+
+impl<C: Clock> Session<C> {
+    pub fn with_clock(clock: C) -> Self {
+        let now = clock.now();
+        Session {
+            clock,
+            phase: Phase::Intro,
+            reasons: Reasons::default(),
+            rules: Rules::default(),
+            filter: InputFilter::default(),
+            countdown_started: now,
+            clock_started: now,
+            failed_time: now,
+            cooldown_started: now,
+            pause_started: now,
+            paused_from: Phase::Tracking,
+            paused: Duration::ZERO,
+            paused_by_focus: false,
+            focused: true,
+            background: false,
+            idle_mismatch: false,
+            ignore_first_input: false,
+            cooldown_duration: Duration::ZERO,
+        }
+    }
+
+    pub fn with_rules(mut self, rules: Rules) -> Self {
+        self.filter = InputFilter::new(rules.sensitivity);
+        self.rules = rules;
+        self
+    }
+
+    pub fn phase(&self) -> Phase {
+        self.phase
+    }
+
+    pub fn rules(&self) -> Rules {
+        self.rules
+    }
+
+    /// The result has been up long enough and only a key press puts it away.
+    pub fn awaiting_key(&self) -> bool {
+        self.phase == Phase::Failed && self.rules.wait_for_key && self.result_shown()
+    }
+
+    /// What's left of the countdown into or back into a run, `None` outside of them.
+    pub fn countdown_left(&self) -> Option<Duration> {
+        match self.phase {
+            Phase::Countdown | Phase::Resuming => Some(self.rules.countdown.saturating_sub(self.clock.now() - self.countdown_started)),
+            _ => None,
+        }
+    }
+
+    pub fn reasons(&self) -> Reasons {
+        self.reasons
+    }
+
+    /// How long the player has stayed inactive, not counting pauses. Frozen while paused
+    /// and once the run fails, and keeps reporting the last run until a new one starts.
+    pub fn elapsed(&self) -> Duration {
+        match self.phase {
+            Phase::Armed | Phase::Tracking => self.active_until(self.clock.now()),
+            Phase::Paused | Phase::Resuming => self.active_until(self.pause_started),
+            Phase::Failed | Phase::Cooldown | Phase::Intro | Phase::Countdown => self.active_until(self.failed_time),
+        }
+    }
+
+    /// Time the current or last run spent paused, including a pause that's still going.
+    pub fn paused(&self) -> Duration {
+        match self.phase {
+            Phase::Paused | Phase::Resuming => self.paused + (self.clock.now() - self.pause_started),
+            _ => self.paused,
+        }
+    }
+
+    pub fn focused(&self) -> bool {
+        self.focused
+    }
+
+    /// Whether the current or last run kept going while the window didn't have focus.
+    pub fn background(&self) -> bool {
+        self.background
+    }
+
+    /// Whether the OS's idle time said the player was active while the current or last run
+    /// was being tracked and nothing came in.
+    pub fn idle_mismatch(&self) -> bool {
+        self.idle_mismatch
+    }
+
+    /// The OS saw input at `at` that the front end never got. Returns `true` when that's
+    /// the first time it happened during this run.
+    pub fn unseen_input(&mut self, at: Instant) -> bool {
+        let tracked = matches!(self.phase, Phase::Armed | Phase::Tracking) && at > self.clock_started;
+        let first = tracked && !self.idle_mismatch;
+        self.idle_mismatch |= tracked;
+        first
+    }
+
+    /// Stops the clock of a run in progress.
+    pub fn pause(&mut self) -> Option<Transition> {
+        self.paused_by_focus = false;
+        match self.phase {
+            Phase::Armed | Phase::Tracking => {
+                self.paused_from = self.phase;
+                self.pause_started = self.clock.now();
+                self.phase = Phase::Paused;
+                Some(Transition::Paused)
+            }
+            _ => None,
+        }
+    }
+
+    /// Goes back into a paused run, through the countdown when there is one.
+    pub fn resume(&mut self) -> Option<Transition> {
+        self.paused_by_focus = false;
+        match self.phase {
+            Phase::Paused if self.rules.countdown.is_zero() => Some(self.unpause(self.clock.now())),
+            Phase::Paused => {
+                self.filter.reset();
+                self.countdown_started = self.clock.now();
+                self.phase = Phase::Resuming;
+                Some(Transition::Resuming)
+            }
+            _ => None,
+        }
+    }
+
+    /// The window went to the background or out of sight, what happens depends on [`FocusPolicy`].
+    pub fn focus_lost(&mut self) -> Option<Transition> {
+        self.focused = false;
+        match (self.phase, self.rules.focus) {
+            (Phase::Armed | Phase::Tracking, FocusPolicy::Fail) => Some(self.fail(Reason::FocusLost)),
+            (Phase::Armed | Phase::Tracking, FocusPolicy::Pause) => {
+                let transition = self.pause();
+                self.paused_by_focus = true;
+                transition
+            }
+            // back to waiting, the countdown starts over once the window is back
+            (Phase::Resuming, FocusPolicy::Pause) => {
+                self.phase = Phase::Paused;
+                self.paused_by_focus = true;
+                Some(Transition::Paused)
+            }
+            (Phase::Armed | Phase::Tracking, FocusPolicy::Ignore) => {
+                self.background = true;
+                None
+            }
+            _ => None,
+        }
+    }
+
+    /// Resumes a run that got paused by losing focus.
+    pub fn focus_gained(&mut self) -> Option<Transition> {
+        self.focused = true;
+        match self.phase {
+            Phase::Paused if self.paused_by_focus => self.resume(),
+            _ => None,
+        }
+    }
+
+    pub fn handle(&mut self, input: Input) -> Option<Transition> {
+        match self.phase {
+            Phase::Intro => match input {
+                Input::KeyDown | Input::MouseButtonDown if self.rules.countdown.is_zero() => {
+                    Some(self.activate(self.clock.now()))
+                }
+                Input::KeyDown | Input::MouseButtonDown => {
+                    self.filter.reset();
+                    self.countdown_started = self.clock.now();
+                    self.phase = Phase::Countdown;
+                    Some(Transition::CountdownStarted)
+                }
+                _ => None,
+            },
+            // goes before the filter, an ignored keyboard still has to be able to put the result away
+            Phase::Failed if input == Input::KeyDown && self.awaiting_key() => Some(self.dismiss()),
+            Phase::Paused => None,
+            _ if !self.filter.accepts(input) => None,
+            Phase::Countdown | Phase::Resuming => match self.rules.countdown_input {
+                CountdownInput::Ignore => None,
+                CountdownInput::Restart => {
+                    self.countdown_started = self.clock.now();
+                    Some(Transition::CountdownRestarted)
+                }
+            },
+            Phase::Armed => {
+                self.ignore_first_input = false;
+                if self.grace_over() {
+                    self.phase = Phase::Tracking;
+                }
+                Some(Transition::Swallowed)
+            }
+            Phase::Tracking => Some(self.fail(input.into())),
+            Phase::Failed => {
+                self.reasons.insert(input.into());
+                Some(Transition::Tallied)
+            }
+            Phase::Cooldown => None,
+        }
+    }
+
+    /// Advances the time based transitions, call once per frame. At most one transition happens per call.
+    /// A run that went live without focus gets its [`FocusPolicy`] here, so `Failed` always means
+    /// [`Reason::FocusLost`].
+    pub fn tick(&mut self) -> Option<Transition> {
+        let now = self.clock.now();
+        match self.phase {
+            // the run went live without focus, after losing it during the countdown or the pause
+            Phase::Armed | Phase::Tracking if !self.focused && self.rules.focus != FocusPolicy::Ignore => {
+                self.focus_lost()
+            }
+            // starts exactly when the countdown ran out, however late this gets called
+            Phase::Countdown if now - self.countdown_started >= self.rules.countdown => {
+                Some(self.activate(self.countdown_started + self.rules.countdown))
+            }
+            Phase::Resuming if now - self.countdown_started >= self.rules.countdown => {
+                Some(self.unpause(self.countdown_started + self.rules.countdown))
+            }
+            Phase::Armed if !self.ignore_first_input && self.grace_over() => {
+                self.phase = Phase::Tracking;
+                Some(Transition::Tracking)
+            }
+            Phase::Failed if !self.rules.wait_for_key && self.result_shown() => Some(self.dismiss()),
+            Phase::Cooldown if now - self.cooldown_started >= self.cooldown_duration => {
+                self.phase = Phase::Intro;
+                Some(Transition::Reset)
+            }
+            _ => None,
+        }
+    }
+
+    fn activate(&mut self, at: Instant) -> Transition {
+        self.reasons = Reasons::default();
+        self.filter.reset();
+        self.clock_started = at;
+        self.paused = Duration::ZERO;
+        self.background = !self.focused && self.rules.focus == FocusPolicy::Ignore;
+        self.idle_mismatch = false;
+        self.ignore_first_input = self.rules.ignore_first_input;
+        self.phase = if self.ignore_first_input || !self.rules.grace_period.is_zero() {
+            Phase::Armed
+        } else {
+            Phase::Tracking
+        };
+        Transition::Activated
+    }
+
+    fn fail(&mut self, reason: Reason) -> Transition {
+        self.reasons.insert(reason);
+        self.failed_time = self.clock.now();
+        self.phase = Phase::Failed;
+        Transition::Failed
+    }
+
+    fn unpause(&mut self, at: Instant) -> Transition {
+        self.paused += at - self.pause_started;
+        self.background |= !self.focused && self.rules.focus == FocusPolicy::Ignore;
+        self.phase = self.paused_from;
+        Transition::Resumed
+    }
+
+    fn dismiss(&mut self) -> Transition {
+        self.phase = Phase::Cooldown;
+        self.cooldown_started = self.clock.now();
+        Transition::Dismissed
+    }
+
+    fn result_shown(&self) -> bool {
+        self.clock.now() - self.failed_time >= self.rules.result_duration
+    }
+
+    fn grace_over(&self) -> bool {
+        self.active_until(self.clock.now()) >= self.rules.grace_period
+    }
+
+    fn active_until(&self, until: Instant) -> Duration {
+        (until - self.clock_started).saturating_sub(self.paused)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn secs(s: u64) -> Duration {
+        Duration::from_secs(s)
+    }
+
+    fn new_session(rules: Rules) -> (Session<ManualClock>, ManualClock, Instant) {
+        let start = Instant::now();
+        let clock = ManualClock::new(start);
+        (Session::with_clock(clock.clone()).with_rules(rules), clock, start)
+    }
+
+    #[test]
+    fn full_cycle() {
+        let (mut session, clock, start) = new_session(Rules::default());
+        assert_eq!(session.phase(), Phase::Intro);
+        assert_eq!(session.tick(), None);
+
+        assert_eq!(session.handle(Input::KeyDown), Some(Transition::CountdownStarted));
+        assert_eq!(session.phase(), Phase::Countdown);
+        clock.set(start + secs(1));
+        assert_eq!(session.tick(), None);
+        assert_eq!(session.countdown_left(), Some(secs(2)));
+
+        // a late frame still starts the run when the countdown ran out
+        clock.set(start + secs(4));
+        assert_eq!(session.tick(), Some(Transition::Activated));
+        assert_eq!(session.phase(), Phase::Armed);
+        assert_eq!(session.elapsed(), secs(1));
+
+        assert_eq!(session.handle(Input::KeyDown), Some(Transition::Swallowed));
+        assert_eq!(session.phase(), Phase::Tracking);
+
+        clock.set(start + secs(13));
+        assert_eq!(session.elapsed(), secs(10));
+        assert_eq!(session.handle(Input::MouseButtonDown), Some(Transition::Failed));
+        assert_eq!(session.phase(), Phase::Failed);
+        assert_eq!(session.reasons().iter().collect::<Vec<_>>(), [Reason::MouseButton]);
+
+        // the result stays up, and the time stays frozen
+        clock.set(start + secs(17));
+        assert_eq!(session.tick(), None);
+        assert_eq!(session.elapsed(), secs(10));
+        clock.set(start + secs(18));
+        assert_eq!(session.tick(), Some(Transition::Dismissed));
+        assert_eq!(session.phase(), Phase::Cooldown);
+        assert_eq!(session.handle(Input::KeyDown), None);
+
+        assert_eq!(session.tick(), Some(Transition::Reset));
+        assert_eq!(session.phase(), Phase::Intro);
+        // the last run is still what's on screen
+        assert_eq!(session.elapsed(), secs(10));
+        assert_eq!(session.handle(Input::KeyDown), Some(Transition::CountdownStarted));
+    }
+
+    #[test]
+    fn reasons_accumulate_until_the_next_run() {
+        let rules = Rules {
+            countdown: Duration::ZERO,
+            ignore_first_input: false,
+            ..Rules::default()
+        };
+        let (mut session, clock, start) = new_session(rules);
+        assert_eq!(session.handle(Input::KeyDown), Some(Transition::Activated));
+        assert_eq!(session.phase(), Phase::Tracking);
+
+        clock.set(start + secs(1));
+        assert_eq!(session.handle(Input::MouseMotion { dx: 3, dy: 0 }), Some(Transition::Failed));
+        assert_eq!(session.handle(Input::KeyDown), Some(Transition::Tallied));
+        assert_eq!(session.handle(Input::MouseMotion { dx: 0, dy: 1 }), Some(Transition::Tallied));
+        assert_eq!(session.handle(Input::ControllerButtonDown), Some(Transition::Tallied));
+        // always in the same order, whatever order they came in
+        assert_eq!(
+            session.reasons().iter().collect::<Vec<_>>(),
+            [Reason::Keyboard, Reason::MouseMotion, Reason::ControllerButton]
+        );
+        assert_eq!(Reasons::from_bits(session.reasons().bits()), session.reasons());
+
+        clock.set(start + secs(6));
+        assert_eq!(session.tick(), Some(Transition::Dismissed));
+        assert_eq!(session.tick(), Some(Transition::Reset));
+        // still there for the result of the last run, gone once the next starts
+        assert!(session.reasons().contains(Reason::Keyboard));
+        assert_eq!(session.handle(Input::KeyDown), Some(Transition::Activated));
+        assert_eq!(session.reasons(), Reasons::default());
+    }
+
+    #[test]
+    fn first_input_is_swallowed_once() {
+        let rules = Rules {
+            countdown: Duration::ZERO,
+            ..Rules::default()
+        };
+        let (mut session, clock, start) = new_session(rules);
+        assert_eq!(session.handle(Input::KeyDown), Some(Transition::Activated));
+        assert_eq!(session.phase(), Phase::Armed);
+
+        // nothing came in, the run stays armed however long it takes
+        clock.set(start + secs(30));
+        assert_eq!(session.tick(), None);
+        assert_eq!(session.phase(), Phase::Armed);
+        assert_eq!(session.elapsed(), secs(30));
+
+        assert_eq!(session.handle(Input::KeyDown), Some(Transition::Swallowed));
+        assert_eq!(session.phase(), Phase::Tracking);
+        assert_eq!(session.handle(Input::KeyDown), Some(Transition::Failed));
+        assert!(session.reasons().contains(Reason::Keyboard));
+    }
+
+    #[test]
+    fn grace_period_outlasts_the_swallowed_input() {
+        let rules = Rules {
+            countdown: Duration::ZERO,
+            grace_period: secs(2),
+            ..Rules::default()
+        };
+        let (mut session, clock, start) = new_session(rules);
+        session.handle(Input::KeyDown);
+        assert_eq!(session.handle(Input::KeyDown), Some(Transition::Swallowed));
+        assert_eq!(session.phase(), Phase::Armed);
+        // everything during the grace period goes the same way
+        clock.set(start + secs(1));
+        assert_eq!(session.handle(Input::MouseButtonDown), Some(Transition::Swallowed));
+        assert_eq!(session.phase(), Phase::Armed);
+        clock.set(start + secs(2));
+        assert_eq!(session.tick(), Some(Transition::Tracking));
+        assert_eq!(session.handle(Input::MouseButtonDown), Some(Transition::Failed));
+        assert_eq!(session.reasons().iter().collect::<Vec<_>>(), [Reason::MouseButton]);
+    }
+
+    #[test]
+    fn countdown_ignores_input_unless_told_to_restart() {
+        let (mut session, clock, start) = new_session(Rules::default());
+        session.handle(Input::KeyDown);
+        clock.set(start + secs(2));
+        assert_eq!(session.handle(Input::KeyDown), None);
+        assert_eq!(session.countdown_left(), Some(secs(1)));
+
+        let rules = Rules {
+            countdown_input: CountdownInput::Restart,
+            ..Rules::default()
+        };
+        let (mut session, clock, start) = new_session(rules);
+        session.handle(Input::KeyDown);
+        clock.set(start + secs(2));
+        assert_eq!(session.handle(Input::KeyDown), Some(Transition::CountdownRestarted));
+        assert_eq!(session.countdown_left(), Some(secs(3)));
+        clock.set(start + secs(4));
+        assert_eq!(session.tick(), None);
+        clock.set(start + secs(5));
+        assert_eq!(session.tick(), Some(Transition::Activated));
+    }
+
+    #[test]
+    fn focus_lost_during_the_countdown_applies_once_the_run_starts() {
+        let (mut session, clock, start) = new_session(Rules::default());
+        session.handle(Input::KeyDown);
+        assert_eq!(session.focus_lost(), None);
+        assert!(!session.focused());
+        clock.set(start + secs(3));
+        assert_eq!(session.tick(), Some(Transition::Activated));
+        assert_eq!(session.tick(), Some(Transition::Failed));
+        assert_eq!(session.reasons().iter().collect::<Vec<_>>(), [Reason::FocusLost]);
+
+        // back in time, nothing happens
+        let (mut session, clock, start) = new_session(Rules::default());
+        session.handle(Input::KeyDown);
+        session.focus_lost();
+        clock.set(start + secs(1));
+        session.focus_gained();
+        clock.set(start + secs(3));
+        assert_eq!(session.tick(), Some(Transition::Activated));
+        assert_eq!(session.tick(), None);
+        assert_eq!(session.phase(), Phase::Armed);
+    }
+
+    #[test]
+    fn focus_lost_before_the_run_pauses_it_until_focus_is_back() {
+        let rules = Rules {
+            focus: FocusPolicy::Pause,
+            ..Rules::default()
+        };
+        let (mut session, clock, start) = new_session(rules);
+        session.handle(Input::KeyDown);
+        session.focus_lost();
+        clock.set(start + secs(3));
+        assert_eq!(session.tick(), Some(Transition::Activated));
+        assert_eq!(session.tick(), Some(Transition::Paused));
+        clock.set(start + secs(10));
+        assert_eq!(session.focus_gained(), Some(Transition::Resuming));
+        clock.set(start + secs(13));
+        assert_eq!(session.tick(), Some(Transition::Resumed));
+        assert_eq!(session.paused(), secs(10));
+    }
+
+    #[test]
+    fn focus_lost_while_paused_applies_on_resume() {
+        let rules = Rules {
+            countdown: Duration::ZERO,
+            ignore_first_input: false,
+            ..Rules::default()
+        };
+        let (mut session, clock, start) = new_session(rules);
+        session.handle(Input::KeyDown);
+        assert_eq!(session.pause(), Some(Transition::Paused));
+        assert_eq!(session.focus_lost(), None);
+        clock.set(start + secs(5));
+        assert_eq!(session.resume(), Some(Transition::Resumed));
+        assert_eq!(session.tick(), Some(Transition::Failed));
+        assert!(session.reasons().contains(Reason::FocusLost));
+    }
+
+    #[test]
+    fn ignored_focus_loss_before_the_run_flags_it() {
+        let rules = Rules {
+            focus: FocusPolicy::Ignore,
+            ..Rules::default()
+        };
+        let (mut session, clock, start) = new_session(rules);
+        session.handle(Input::KeyDown);
+        session.focus_lost();
+        clock.set(start + secs(3));
+        assert_eq!(session.tick(), Some(Transition::Activated));
+        assert_eq!(session.tick(), None);
+        assert!(session.background());
+
+        // the next run starts with focus and a clean slate
+        let (mut session, clock, start) = new_session(rules);
+        session.handle(Input::KeyDown);
+        clock.set(start + secs(3));
+        session.tick();
+        assert!(!session.background());
+    }
+
+    #[test]
+    fn wait_for_key_keeps_the_result_up() {
+        let rules = Rules {
+            countdown: Duration::ZERO,
+            ignore_first_input: false,
+            wait_for_key: true,
+            ..Rules::default()
+        };
+        let (mut session, clock, start) = new_session(rules);
+        session.handle(Input::KeyDown);
+        session.handle(Input::KeyDown);
+        clock.set(start + secs(60));
+        assert_eq!(session.tick(), None);
+        assert!(session.awaiting_key());
+        assert_eq!(session.handle(Input::MouseButtonDown), Some(Transition::Tallied));
+        assert_eq!(session.handle(Input::KeyDown), Some(Transition::Dismissed));
+    }
+}
@@ -0,0 +1,226 @@
+use std::collections::HashMap;
+
+use sdl2::pixels::{Color, PixelFormatEnum};
+use sdl2::rect::Rect;
+use sdl2::render::{BlendMode, Canvas, Texture, TextureCreator};
+use sdl2::surface::Surface;
+use sdl2::ttf::Font;
+use sdl2::video::{Window, WindowContext};
+
+const ATLAS_WIDTH: u32 = 1024;
+
+#[derive(Debug, Clone, Copy)]
+struct Glyph {
+    /// Where the glyph sits in the atlas, `None` for whitespace.
+    src: Option<Rect>,
+    advance: i32,
+}
+
+/// Laid out text, only redone when the text or the width it wraps to changes.
+#[derive(Debug, Default)]
+pub struct Label {
+    text: String,
+    max_width: Option<u32>,
+    lines: Vec<Line>,
+}
+
+#[derive(Debug)]
+struct Line {
+    width: i32,
+    /// Atlas rect and x offset of every visible glyph.
+    glyphs: Vec<(Rect, i32)>,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub enum Align {
+    Left(i32),
+    /// Centred inside `0..width`.
+    Center(u32),
+}
+
+/// Rasterizes each glyph of a font once into a single texture and draws text out of it,
+/// instead of rendering and uploading a new surface for every string every frame.
+pub struct GlyphAtlas<'a> {
+    font: &'a Font<'a, 'static>,
+    tc: &'a TextureCreator<WindowContext>,
+    color: Color,
+    alpha: u8,
+    glyphs: HashMap<char, Glyph>,
+    surface: Surface<'static>,
+    texture: Option<Texture<'a>>,
+    /// Top left corner of the next free slot.
+    cursor: (i32, i32),
+}
+
+impl<'a> GlyphAtlas<'a> {
+    pub fn new(
+        font: &'a Font<'a, 'static>,
+        tc: &'a TextureCreator<WindowContext>,
+        color: Color,
+    ) -> Result<Self, String> {
+        let surface = Surface::new(ATLAS_WIDTH, font.height() as u32 * 4, PixelFormatEnum::ARGB8888)?;
+        Ok(GlyphAtlas {
+            font,
+            tc,
+            color,
+            alpha: 255,
+            glyphs: HashMap::new(),
+            surface,
+            texture: None,
+            cursor: (0, 0),
+        })
+    }
+
+    pub fn height(&self, label: &Label) -> i32 {
+        label.lines.len() as i32 * self.font.height()
+    }
+
+    fn glyph(&mut self, ch: char) -> Result<Glyph, String> {
+        if let Some(g) = self.glyphs.get(&ch) {
+            return Ok(*g);
+        }
+        // characters the font doesn't have show up as '?'
+        let shown = if self.font.find_glyph_metrics(ch).is_some() { ch } else { '?' };
+        let advance = self.font.find_glyph_metrics(shown).map(|m| m.advance).unwrap_or(0);
+        let src = if shown.is_whitespace() {
+            None
+        } else {
+            let mut rendered = self
+                .font
+                .render_char(shown)
+                .blended(Color::WHITE)
+                .map_err(|e| e.to_string())?;
+            Some(self.insert(&mut rendered)?)
+        };
+        let glyph = Glyph { src, advance };
+        self.glyphs.insert(ch, glyph);
+        Ok(glyph)
+    }
+
+    /// Copies a rendered glyph into the next free slot, growing the atlas when it's full.
+    fn insert(&mut self, rendered: &mut Surface) -> Result<Rect, String> {
+        let (w, h) = (rendered.width(), rendered.height());
+        if self.cursor.0 + w as i32 > ATLAS_WIDTH as i32 {
+            self.cursor = (0, self.cursor.1 + self.font.height());
+        }
+        while self.cursor.1 + h as i32 > self.surface.height() as i32 {
+            let mut grown = Surface::new(ATLAS_WIDTH, self.surface.height() * 2, PixelFormatEnum::ARGB8888)?;
+            self.surface.set_blend_mode(BlendMode::None)?;
+            self.surface.blit(None, &mut grown, None)?;
+            self.surface = grown;
+        }
+        let dst = Rect::new(self.cursor.0, self.cursor.1, w, h);
+        // copy the alpha as is instead of blending it onto the (transparent) atlas
+        rendered.set_blend_mode(BlendMode::None)?;
+        rendered.blit(None, &mut self.surface, dst)?;
+        self.cursor.0 += w as i32;
+        self.texture = None;
+        Ok(dst)
+    }
+
+    fn width_of(&mut self, text: &str) -> Result<i32, String> {
+        let mut width = 0;
+        for ch in text.chars() {
+            width += self.glyph(ch)?.advance;
+        }
+        Ok(width)
+    }
+
+    /// Word wraps `text` to `max_width` (if any) and lays it out into `label`. Does
+    /// nothing when the label already holds that text at that width.
+    pub fn layout(&mut self, label: &mut Label, text: &str, max_width: Option<u32>) -> Result<(), String> {
+        if label.text == text && label.max_width == max_width {
+            return Ok(());
+        }
+        label.lines.clear();
+        let wrapped = match max_width {
+            Some(w) => self.word_wrap(text, w as i32)?,
+            None => text.lines().map(str::to_string).collect(),
+        };
+        for line in wrapped {
+            let mut glyphs = Vec::with_capacity(line.len());
+            let mut x = 0;
+            for ch in line.chars() {
+                let g = self.glyph(ch)?;
+                if let Some(src) = g.src {
+                    glyphs.push((src, x));
+                }
+                x += g.advance;
+            }
+            label.lines.push(Line { width: x, glyphs });
+        }
+        label.text.clear();
+        label.text.push_str(text);
+        label.max_width = max_width;
+        Ok(())
+    }
+
+    fn word_wrap(&mut self, text: &str, max_width: i32) -> Result<Vec<String>, String> {
+        let space = self.glyph(' ')?.advance;
+        let mut lines: Vec<String> = Vec::new();
+        let mut current_line = String::new();
+        let mut current_width = 0;
+
+        for word in text.split_whitespace() {
+            let word_width = self.width_of(word)?;
+            if current_line.is_empty() {
+                current_line.push_str(word);
+                current_width = word_width;
+            } else if current_width + space + word_width <= max_width {
+                current_line.push(' ');
+                current_line.push_str(word);
+                current_width += space + word_width;
+            } else {
+                lines.push(std::mem::take(&mut current_line));
+                current_line.push_str(word);
+                current_width = word_width;
+            }
+        }
+        if !current_line.is_empty() {
+            lines.push(current_line);
+        }
+        Ok(lines)
+    }
+
+    /// Opacity of everything drawn from now on.
+    pub fn set_alpha(&mut self, alpha: u8) {
+        self.alpha = alpha;
+    }
+
+    /// Draws the label with its first line at `y`. Returns the y right below the last line.
+    pub fn draw(
+        &mut self,
+        canvas: &mut Canvas<Window>,
+        label: &Label,
+        align: Align,
+        mut y: i32,
+    ) -> Result<i32, String> {
+        if self.texture.is_none() {
+            let mut texture = self
+                .tc
+                .create_texture_from_surface(&self.surface)
+                .map_err(|e| e.to_string())?;
+            texture.set_blend_mode(BlendMode::Blend);
+            texture.set_color_mod(self.color.r, self.color.g, self.color.b);
+            self.texture = Some(texture);
+        }
+        let texture = self.texture.as_mut().unwrap();
+        texture.set_alpha_mod(self.alpha);
+        let line_height = self.font.height();
+        for line in &label.lines {
+            let x = match align {
+                Align::Left(x) => x,
+                Align::Center(width) => (width as i32 - line.width) / 2,
+            };
+            for (src, offset) in &line.glyphs {
+                canvas.copy(
+                    texture,
+                    *src,
+                    Rect::new(x + offset, y, src.width(), src.height()),
+                )?;
+            }
+            y += line_height;
+        }
+        Ok(y)
+    }
+}